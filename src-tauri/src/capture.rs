@@ -0,0 +1,121 @@
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::netns;
+use crate::proc_root;
+
+const NET_FILES: [&str; 5] = ["tcp", "tcp6", "udp", "udp6", "unix"];
+const PROCESS_FILES: [&str; 4] = ["status", "cmdline", "stat", "cgroup"];
+
+// procfs reports a size of 0, so read and write instead of fs::copy
+fn copy_proc_file(from: &Path, to: &Path) {
+    if let Ok(content) = fs::read(from) {
+        let _ = fs::write(to, content);
+    }
+}
+
+fn copy_link(from: &Path, to: &Path) {
+    if let Ok(target) = fs::read_link(from) {
+        let _ = symlink(target, to);
+    }
+}
+
+fn capture_net_dir(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("{}: {}", to.display(), e))?;
+    for file in NET_FILES {
+        copy_proc_file(&from.join(file), &to.join(file));
+    }
+    Ok(())
+}
+
+// Copies only what the scanner reads; fd links are kept for sockets only
+fn capture_process(pid: i32, out: &Path) {
+    let src = PathBuf::from(format!("/proc/{}", pid));
+    let dst = out.join(pid.to_string());
+    if fs::create_dir_all(dst.join("fd")).is_err() || fs::create_dir_all(dst.join("ns")).is_err() {
+        return;
+    }
+
+    for file in PROCESS_FILES {
+        copy_proc_file(&src.join(file), &dst.join(file));
+    }
+    copy_link(&src.join("ns/net"), &dst.join("ns/net"));
+    copy_link(&src.join("cwd"), &dst.join("cwd"));
+    copy_link(&src.join("exe"), &dst.join("exe"));
+
+    if let Ok(fds) = fs::read_dir(src.join("fd")) {
+        for fd in fds.flatten() {
+            if let Ok(target) = fs::read_link(fd.path()) {
+                if target.to_string_lossy().starts_with("socket:[") {
+                    let _ = symlink(target, dst.join("fd").join(fd.file_name()));
+                }
+            }
+        }
+    }
+}
+
+fn capture_tree(out: &Path) -> Result<(), String> {
+    capture_net_dir(Path::new("/proc/net"), &out.join("net"))?;
+
+    for pid in proc_root::list_pids() {
+        capture_process(pid, out);
+    }
+
+    // Sockets of the other namespaces, through one member process each
+    for ns in netns::network_namespaces() {
+        if let Some(rel) = ns.net_dir.strip_prefix("/proc/") {
+            if rel != "net" {
+                capture_net_dir(Path::new(&ns.net_dir), &out.join(rel))?;
+            }
+        }
+    }
+
+    // Replays resolve "self" to the process that took the capture
+    let _ = symlink(std::process::id().to_string(), out.join("self"));
+    // Boot time, needed to turn process start ticks into a date
+    copy_proc_file(Path::new("/proc/stat"), &out.join("stat"));
+    copy_proc_file(Path::new("/etc/passwd"), &out.join("passwd"));
+    Ok(())
+}
+
+// Writes a capture directory, or a tarball when `output` ends in .tar.gz/.tgz
+pub(crate) fn capture_impl(output: &Path) -> Result<PathBuf, String> {
+    if !proc_root::is_live() {
+        return Err("Cannot capture while replaying a capture".to_string());
+    }
+    if output.exists() {
+        return Err(format!("{} already exists", output.display()));
+    }
+
+    let name = output.to_string_lossy();
+    let stem = name.strip_suffix(".tar.gz").or_else(|| name.strip_suffix(".tgz"));
+    let Some(stem) = stem else {
+        capture_tree(output)?;
+        return Ok(output.to_path_buf());
+    };
+
+    let dir = PathBuf::from(stem);
+    if dir.exists() {
+        return Err(format!("{} already exists", dir.display()));
+    }
+    capture_tree(&dir)?;
+
+    let parent = dir.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let base = dir.file_name().ok_or_else(|| format!("Invalid output path {}", name))?;
+    let status = Command::new("tar")
+        .arg("czf")
+        .arg(output)
+        .arg("-C")
+        .arg(parent)
+        .arg(base)
+        .status()
+        .map_err(|e| format!("Failed to execute tar: {}", e))?;
+    let _ = fs::remove_dir_all(&dir);
+
+    if !status.success() {
+        return Err("tar failed to write the archive".to_string());
+    }
+    Ok(output.to_path_buf())
+}
@@ -0,0 +1,365 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::PortInfo;
+
+// Dev tools and servers recognised by process name, checked before anything port based
+const PROCESSES: [(&str, &str, &str); 24] = [
+    ("vite", "vite", "web"),
+    ("next-server", "next.js", "web"),
+    ("webpack", "webpack", "web"),
+    ("nginx", "nginx", "web"),
+    ("httpd", "apache", "web"),
+    ("apache2", "apache", "web"),
+    ("caddy", "caddy", "web"),
+    ("php-fpm", "php-fpm", "web"),
+    ("postgres", "postgresql", "database"),
+    ("mysqld", "mysql", "database"),
+    ("mariadbd", "mariadb", "database"),
+    ("mongod", "mongodb", "database"),
+    ("redis-server", "redis", "database"),
+    ("memcached", "memcached", "database"),
+    ("clickhouse-server", "clickhouse", "database"),
+    ("beam.smp", "rabbitmq", "messaging"),
+    ("nats-server", "nats", "messaging"),
+    ("mosquitto", "mqtt", "messaging"),
+    ("sshd", "ssh", "system"),
+    ("systemd-resolve", "dns", "system"),
+    ("dnsmasq", "dns", "system"),
+    ("cupsd", "ipp", "system"),
+    ("avahi-daemon", "mdns", "system"),
+    ("smbd", "smb", "system"),
+];
+
+// Well-known development ports that /etc/services has no (useful) name for
+const PORTS: [(u16, &str, &str); 34] = [
+    (3000, "dev-server", "web"),
+    (3001, "dev-server", "web"),
+    (4200, "angular", "web"),
+    (5000, "dev-server", "web"),
+    (5173, "vite", "web"),
+    (5174, "vite", "web"),
+    (6006, "storybook", "web"),
+    (8000, "http-dev", "web"),
+    (8080, "http-alt", "web"),
+    (8443, "https-alt", "web"),
+    (8888, "jupyter", "web"),
+    (19000, "expo", "web"),
+    (1433, "mssql", "database"),
+    (1521, "oracle", "database"),
+    (3306, "mysql", "database"),
+    (5432, "postgresql", "database"),
+    (5433, "postgresql", "database"),
+    (5984, "couchdb", "database"),
+    (6379, "redis", "database"),
+    (8086, "influxdb", "database"),
+    (9042, "cassandra", "database"),
+    (9200, "elasticsearch", "database"),
+    (11211, "memcached", "database"),
+    (26379, "redis-sentinel", "database"),
+    (27017, "mongodb", "database"),
+    (1883, "mqtt", "messaging"),
+    (4222, "nats", "messaging"),
+    (5672, "amqp", "messaging"),
+    (9092, "kafka", "messaging"),
+    (15672, "rabbitmq", "messaging"),
+    (9229, "node-inspector", "devtools"),
+    (5005, "jdwp", "devtools"),
+    (2345, "delve", "devtools"),
+    (9090, "prometheus", "devtools"),
+];
+
+// Every category classify() can give without user rules
+pub(crate) const CATEGORIES: [&str; 6] = ["web", "database", "messaging", "devtools", "system", "other"];
+
+// Category for what the probe heard on the port
+fn category_for_protocol(protocol: &str) -> Option<&'static str> {
+    match protocol {
+        "http" | "tls" => Some("web"),
+        "postgresql" | "mysql" | "redis" | "mongodb" => Some("database"),
+        "ssh" | "smtp" | "imap" | "pop3" | "ftp" => Some("system"),
+        _ => None,
+    }
+}
+
+// Category for an /etc/services name
+fn category_for_service(service: &str) -> Option<&'static str> {
+    match service {
+        "http" | "https" | "http-alt" | "webcache" | "tproxy" => Some("web"),
+        "postgresql" | "mysql" | "ms-sql-s" | "mongodb" | "redis" | "memcache" => Some("database"),
+        "amqp" | "amqps" | "mqtt" | "xmpp-client" => Some("messaging"),
+        _ => None,
+    }
+}
+
+// One `[[category]]` table of config.toml; every condition given has to match
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CategoryRule {
+    pub category: String,
+    // "8080" or "8000-8100"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ports: Option<String>,
+    // Process name, as in `ps -o comm`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    // Regex matched against the full command line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cmdline: Option<String>,
+    // Overrides service_name for matching ports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+}
+
+fn parse_range(ports: &str) -> Option<(u16, u16)> {
+    match ports.split_once('-') {
+        Some((from, to)) => Some((from.trim().parse().ok()?, to.trim().parse().ok()?)),
+        None => {
+            let port = ports.trim().parse().ok()?;
+            Some((port, port))
+        }
+    }
+}
+
+struct CompiledRule {
+    rule: CategoryRule,
+    range: Option<(u16, u16)>,
+    cmdline: Option<Regex>,
+}
+
+impl CompiledRule {
+    fn new(rule: CategoryRule) -> Result<Self, String> {
+        let range = match &rule.ports {
+            Some(ports) => Some(parse_range(ports).filter(|(from, to)| from <= to).ok_or_else(|| format!("Invalid port range '{}'", ports))?),
+            None => None,
+        };
+        let cmdline = match &rule.cmdline {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| format!("Invalid cmdline pattern '{}': {}", pattern, e))?),
+            None => None,
+        };
+        if range.is_none() && rule.process.is_none() && cmdline.is_none() {
+            return Err(format!("Rule for '{}' needs ports, process or cmdline", rule.category));
+        }
+        Ok(CompiledRule { rule, range, cmdline })
+    }
+
+    fn matches(&self, port: u16, info: &PortInfo) -> bool {
+        if let Some((from, to)) = self.range {
+            if port < from || port > to {
+                return false;
+            }
+        }
+        if let Some(process) = &self.rule.process {
+            if info.process_name.as_deref() != Some(process.as_str()) {
+                return false;
+            }
+        }
+        if let Some(re) = &self.cmdline {
+            if !info.cmdline.as_deref().map(|c| re.is_match(c)).unwrap_or(false) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+pub(crate) fn validate_rule(rule: &CategoryRule) -> Result<(), String> {
+    CompiledRule::new(rule.clone()).map(|_| ())
+}
+
+// (port, protocol) -> name from /etc/services, read once, it does not change while we run
+fn services() -> &'static HashMap<(u16, String), String> {
+    static SERVICES: OnceLock<HashMap<(u16, String), String>> = OnceLock::new();
+    SERVICES.get_or_init(load_services)
+}
+
+fn load_services() -> HashMap<(u16, String), String> {
+    let mut services = HashMap::new();
+    let Ok(content) = fs::read_to_string("/etc/services") else { return services; };
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(spec)) = (fields.next(), fields.next()) else { continue; };
+        let Some((port, protocol)) = spec.split_once('/') else { continue; };
+        if let Ok(port) = port.parse::<u16>() {
+            services.entry((port, protocol.to_string())).or_insert_with(|| name.to_string());
+        }
+    }
+    services
+}
+
+// Compiles the rules once per scan
+pub(crate) struct Classifier {
+    rules: Vec<CompiledRule>,
+    services: &'static HashMap<(u16, String), String>,
+}
+
+impl Classifier {
+    pub fn new(rules: &[CategoryRule]) -> Self {
+        Classifier {
+            // config::load() already dropped files with invalid rules
+            rules: rules.iter().cloned().filter_map(|r| CompiledRule::new(r).ok()).collect(),
+            services: services(),
+        }
+    }
+
+    // (category, service name): user rules, then known processes, the probed protocol,
+    // known ports and /etc/services; anything else below 1024 is "system"
+    pub fn classify(&self, info: &PortInfo) -> (String, Option<String>) {
+        let port: u16 = info.port.parse().unwrap_or(0);
+        let service = self.services.get(&(port, info.protocol.clone())).cloned();
+
+        if let Some(rule) = self.rules.iter().find(|r| r.matches(port, info)) {
+            return (rule.rule.category.clone(), rule.rule.service.clone().or(service));
+        }
+        let process = info.process_name.as_deref().unwrap_or("");
+        if let Some((_, name, category)) = PROCESSES.iter().find(|(p, _, _)| *p == process) {
+            return (category.to_string(), Some(name.to_string()));
+        }
+        if let Some(category) = info.protocol_guess.as_deref().and_then(category_for_protocol) {
+            // A web server on 5432 is not "postgresql"
+            let service = service.filter(|s| category_for_service(s) == Some(category));
+            return (category.to_string(), service.or_else(|| info.protocol_guess.clone()));
+        }
+        if let Some((_, name, category)) = PORTS.iter().find(|(p, _, _)| *p == port) {
+            return (category.to_string(), Some(name.to_string()));
+        }
+        if let Some(category) = service.as_deref().and_then(category_for_service) {
+            return (category.to_string(), service);
+        }
+        let category = if port < 1024 { "system" } else { "other" };
+        (category.to_string(), service)
+    }
+}
+
+pub(crate) fn classify(ports: &mut [PortInfo], rules: &[CategoryRule]) {
+    let classifier = Classifier::new(rules);
+    for port in ports.iter_mut() {
+        let (category, service) = classifier.classify(port);
+        port.category = category;
+        port.service_name = service;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fixed /etc/services, the host's differs between distributions
+    fn classifier(rules: &[CategoryRule]) -> Classifier {
+        let services = [(5432, "postgresql"), (443, "https"), (631, "ipp"), (9090, "websm"), (3306, "mysql")]
+            .into_iter()
+            .map(|(port, name)| ((port, "tcp".to_string()), name.to_string()))
+            .collect();
+        Classifier {
+            rules: rules.iter().cloned().map(|r| CompiledRule::new(r).unwrap()).collect(),
+            services: Box::leak(Box::new(services)),
+        }
+    }
+
+    fn port(port: &str, process: &str, guess: Option<&str>) -> PortInfo {
+        PortInfo {
+            port: port.into(),
+            protocol: "tcp".into(),
+            process_name: Some(process.into()),
+            protocol_guess: guess.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn result(category: &str, service: Option<&str>) -> (String, Option<String>) {
+        (category.to_string(), service.map(String::from))
+    }
+
+    #[test]
+    fn user_rules_come_first() {
+        let rules = [
+            CategoryRule { category: "proxy".into(), process: Some("nginx".into()), service: Some("edge".into()), ..Default::default() },
+            CategoryRule { category: "work".into(), ports: Some("5400-5500".into()), ..Default::default() },
+        ];
+        let c = classifier(&rules);
+        assert_eq!(c.classify(&port("80", "nginx", Some("http"))), result("proxy", Some("edge")));
+        // Without a service of its own the rule keeps the /etc/services name
+        assert_eq!(c.classify(&port("5432", "postgres", None)), result("work", Some("postgresql")));
+    }
+
+    #[test]
+    fn every_condition_of_a_rule_has_to_match() {
+        let rule = CategoryRule {
+            category: "api".into(),
+            ports: Some("8000-8100".into()),
+            cmdline: Some("uvicorn".into()),
+            ..Default::default()
+        };
+        let c = classifier(&[rule]);
+        let mut info = port("8000", "python3", None);
+        info.cmdline = Some("python3 -m uvicorn app:main".into());
+        assert_eq!(c.classify(&info).0, "api");
+        info.port = "8200".into();
+        assert_eq!(c.classify(&info).0, "other");
+        info.port = "8000".into();
+        info.cmdline = Some("python3 -m http.server".into());
+        assert_eq!(c.classify(&info), result("web", Some("http-dev")));
+    }
+
+    #[test]
+    fn known_processes_beat_the_probe_and_the_port() {
+        let c = classifier(&[]);
+        assert_eq!(c.classify(&port("3000", "postgres", Some("http"))), result("database", Some("postgresql")));
+        assert_eq!(c.classify(&port("9999", "vite", None)), result("web", Some("vite")));
+    }
+
+    #[test]
+    fn the_probe_beats_the_port_tables() {
+        let c = classifier(&[]);
+        assert_eq!(c.classify(&port("3000", "python3", Some("postgresql"))), result("database", Some("postgresql")));
+        assert_eq!(c.classify(&port("6379", "node", Some("http"))), result("web", Some("http")));
+        // Nothing the categories know: on to the port tables
+        assert_eq!(c.classify(&port("6379", "node", Some("unknown"))), result("database", Some("redis")));
+    }
+
+    #[test]
+    fn a_web_server_on_5432_is_not_postgresql() {
+        let c = classifier(&[]);
+        assert_eq!(c.classify(&port("5432", "node", Some("http"))), result("web", Some("http")));
+        assert_eq!(c.classify(&port("5432", "node", Some("postgresql"))), result("database", Some("postgresql")));
+        assert_eq!(c.classify(&port("3306", "node", Some("tls"))), result("web", Some("tls")));
+    }
+
+    #[test]
+    fn known_ports_then_etc_services_then_the_fallback() {
+        let c = classifier(&[]);
+        // The built-in table wins over /etc/services
+        assert_eq!(c.classify(&port("9090", "app", None)), result("devtools", Some("prometheus")));
+        assert_eq!(c.classify(&port("443", "app", None)), result("web", Some("https")));
+        // A service name without a category is kept
+        assert_eq!(c.classify(&port("631", "app", None)), result("system", Some("ipp")));
+        assert_eq!(c.classify(&port("999", "app", None)), result("system", None));
+        assert_eq!(c.classify(&port("40000", "app", None)), result("other", None));
+    }
+
+    #[test]
+    fn port_ranges_parse() {
+        assert_eq!(parse_range("8080"), Some((8080, 8080)));
+        assert_eq!(parse_range("8000-8100"), Some((8000, 8100)));
+        assert_eq!(parse_range(" 8000 - 8100 "), Some((8000, 8100)));
+        assert_eq!(parse_range("8000-"), None);
+        assert_eq!(parse_range("http"), None);
+        assert_eq!(parse_range("70000"), None);
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let empty = CategoryRule { category: "web".into(), service: Some("www".into()), ..Default::default() };
+        assert_eq!(validate_rule(&empty), Err("Rule for 'web' needs ports, process or cmdline".to_string()));
+
+        let inverted = CategoryRule { category: "web".into(), ports: Some("8100-8000".into()), ..Default::default() };
+        assert!(validate_rule(&inverted).is_err());
+        let bad_regex = CategoryRule { category: "web".into(), cmdline: Some("(".into()), ..Default::default() };
+        assert!(validate_rule(&bad_regex).is_err());
+        let process = CategoryRule { category: "web".into(), process: Some("caddy".into()), ..Default::default() };
+        assert!(validate_rule(&process).is_ok());
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::Path;
+
+use crate::proc_root::proc_path;
+
+// Runtimes whose comm says nothing about what they are running
+const INTERPRETERS: [&str; 10] = ["node", "python", "java", "ruby", "perl", "php", "deno", "bun", "dotnet", "beam.smp"];
+
+// Flags that take the next argument as their value
+const VALUE_FLAGS: [&str; 6] = ["-cp", "-classpath", "--class-path", "-r", "--require", "--import"];
+
+const MAX_DISPLAY_LEN: usize = 48;
+
+// Command line, working directory and executable of a process
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ProcessDetails {
+    pub cmdline: Option<String>,
+    pub cwd: Option<String>,
+    pub exe: Option<String>,
+    pub display_name: String,
+}
+
+fn read_link(pid: i32, name: &str) -> Option<String> {
+    fs::read_link(proc_path(&format!("{}/{}", pid, name)))
+        .ok()
+        .map(|p| p.to_string_lossy().into_owned())
+}
+
+fn read_args(pid: i32) -> Vec<String> {
+    let Ok(raw) = fs::read(proc_path(&format!("{}/cmdline", pid))) else { return Vec::new(); };
+    raw.split(|b| *b == 0)
+        .filter(|a| !a.is_empty())
+        .map(|a| String::from_utf8_lossy(a).into_owned())
+        .collect()
+}
+
+fn base_name(path: &str) -> &str {
+    Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or(path)
+}
+
+fn is_interpreter(program: &str) -> bool {
+    // "python3.12" -> "python"
+    let stem = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    INTERPRETERS.contains(&stem) || INTERPRETERS.contains(&program)
+}
+
+// "node vite", "python3 manage.py runserver", "python3 -m http.server", "java app.jar"
+pub(crate) fn display_name(name: &str, args: &[String]) -> String {
+    let Some(first) = args.first() else { return name.to_string(); };
+
+    // Programs that rewrite their title (nginx, postgres, gunicorn) pack it into argv[0]
+    if args.len() == 1 && first.contains(' ') {
+        return truncate(first.trim());
+    }
+
+    let program = base_name(first);
+    if !is_interpreter(program) && !is_interpreter(name) {
+        return truncate(program);
+    }
+
+    let mut parts = vec![program.to_string()];
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-m" | "-jar" => {
+                if let Some(target) = rest.next() {
+                    if arg == "-m" {
+                        parts.push(format!("-m {}", target));
+                    } else {
+                        parts.push(base_name(target).to_string());
+                    }
+                }
+                break;
+            }
+            a if VALUE_FLAGS.contains(&a) => {
+                rest.next();
+            }
+            a if a.starts_with('-') => {}
+            a => {
+                parts.push(base_name(a).to_string());
+                break;
+            }
+        }
+    }
+
+    // A subcommand right after the script, e.g. "manage.py runserver", "vite dev"
+    if let Some(sub) = rest.next() {
+        if sub.starts_with(|c: char| c.is_ascii_alphabetic()) && sub.chars().all(|c| c.is_ascii_alphanumeric() || "_-:".contains(c)) {
+            parts.push(sub.clone());
+        }
+    }
+
+    truncate(&parts.join(" "))
+}
+
+fn truncate(name: &str) -> String {
+    if name.chars().count() <= MAX_DISPLAY_LEN {
+        return name.to_string();
+    }
+    let mut short: String = name.chars().take(MAX_DISPLAY_LEN - 1).collect();
+    short.push('…');
+    short
+}
+
+// The full command line, for rules that match on it
+pub(crate) fn cmdline_of(pid: i32) -> Option<String> {
+    let args = read_args(pid);
+    if args.is_empty() { None } else { Some(args.join(" ")) }
+}
+
+// File name of argv[0], the untruncated name when comm was cut at 15 bytes
+pub(crate) fn program_of(pid: i32) -> Option<String> {
+    read_args(pid).first().map(|a| base_name(a).to_string())
+}
+
+pub(crate) fn process_details(pid: i32, name: &str) -> ProcessDetails {
+    let args = read_args(pid);
+    ProcessDetails {
+        display_name: display_name(name, &args),
+        cmdline: if args.is_empty() { None } else { Some(args.join(" ")) },
+        cwd: read_link(pid, "cwd"),
+        exe: read_link(pid, "exe"),
+    }
+}
@@ -0,0 +1,372 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::classifier::CategoryRule;
+use crate::sources::SourceKind;
+use crate::terminate::TerminationPolicy;
+
+// A single port or an inclusive range: 80 or "8000-8100"
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PortSpec {
+    Port(u16),
+    Range(String),
+}
+
+impl PortSpec {
+    fn bounds(&self) -> Result<(u16, u16), String> {
+        match self {
+            PortSpec::Port(port) => Ok((*port, *port)),
+            PortSpec::Range(range) => {
+                let parse = |s: &str| s.trim().parse::<u16>().map_err(|_| format!("Invalid port range '{}'", range));
+                match range.split_once('-') {
+                    Some((from, to)) => Ok((parse(from)?, parse(to)?)),
+                    None => parse(range).map(|p| (p, p)),
+                }
+            }
+        }
+    }
+
+    fn contains(&self, port: u16) -> bool {
+        self.bounds().map(|(from, to)| port >= from && port <= to).unwrap_or(false)
+    }
+}
+
+// One set of conditions; a socket or process matches if any entry matches
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct MatchRules {
+    pub ports: Vec<PortSpec>,
+    // Process names, `*` matches any run of characters ("systemd*")
+    pub processes: Vec<String>,
+    pub users: Vec<String>,
+    // Regexes matched against the full command line
+    pub cmdlines: Vec<String>,
+}
+
+impl MatchRules {
+    fn validate(&self) -> Result<(), String> {
+        for spec in &self.ports {
+            spec.bounds()?;
+        }
+        for pattern in &self.cmdlines {
+            Regex::new(pattern).map_err(|e| format!("Invalid cmdline pattern '{}': {}", pattern, e))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    // Socket backend, overridden by --source and $PPKILLER_SOURCE
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceKind>,
+    // Hidden from every list...
+    pub ignore: MatchRules,
+    // ...unless one of these matches, e.g. a dev server on port 80
+    pub include: MatchRules,
+    // [kill]: signal, grace period and escalation used by every kill
+    pub kill: TerminationPolicy,
+    // [[category]] tables, see classifier.rs
+    #[serde(rename = "category", skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<CategoryRule>,
+}
+
+impl Default for Config {
+    // What used to be hard-coded: DNS, Samba, CUPS, SSH, HTTP(S), desktop daemons,
+    // kernel threads and everything owned by system users
+    fn default() -> Self {
+        Config {
+            source: None,
+            ignore: MatchRules {
+                ports: [53, 139, 445, 631, 22, 80, 443].into_iter().map(PortSpec::Port).collect(),
+                processes: [
+                    "systemd*", "dbus*", "NetworkManager", "pipewire*", "pulseaudio",
+                    "cupsd", "smbd", "nmbd", "avahi-daemon", "libvirtd", "dnsmasq", "sshd",
+                    "kthreadd", "ksoftirqd*", "migration*", "rcu_*", "watchdog*", "kworker*",
+                ].into_iter().map(String::from).collect(),
+                users: ["root", "system", "daemon", "nobody"].into_iter().map(String::from).collect(),
+                cmdlines: Vec::new(),
+            },
+            include: MatchRules::default(),
+            kill: TerminationPolicy::default(),
+            categories: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<(), String> {
+        self.ignore.validate()?;
+        self.include.validate()?;
+        self.kill.validate()?;
+        for rule in &self.categories {
+            crate::classifier::validate_rule(rule)?;
+        }
+        Ok(())
+    }
+}
+
+// `*` matches any run of characters, everything else literally
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text[first.len()..].ends_with(last) || text.len() < first.len() + last.len() {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+// What the rules look at; None for anything unknown (no owner, no cmdline)
+#[derive(Default)]
+pub(crate) struct Subject<'a> {
+    pub port: Option<u16>,
+    pub process: Option<&'a str>,
+    pub user: Option<&'a str>,
+    pub cmdline: Option<&'a str>,
+}
+
+struct CompiledRules {
+    rules: MatchRules,
+    cmdlines: Vec<Regex>,
+}
+
+impl CompiledRules {
+    fn new(rules: MatchRules) -> Self {
+        let cmdlines = rules.cmdlines.iter().filter_map(|p| Regex::new(p).ok()).collect();
+        CompiledRules { rules, cmdlines }
+    }
+
+    fn matches(&self, s: &Subject) -> bool {
+        s.port.is_some_and(|port| self.rules.ports.iter().any(|spec| spec.contains(port)))
+            || s.process.is_some_and(|name| self.rules.processes.iter().any(|p| glob_match(p, name)))
+            || s.user.is_some_and(|user| self.rules.users.iter().any(|u| u == user))
+            || s.cmdline.is_some_and(|cmdline| self.cmdlines.iter().any(|re| re.is_match(cmdline)))
+    }
+}
+
+// The ignore/include rules shared by the port, process and socket scanners
+pub(crate) struct RuleSet {
+    ignore: CompiledRules,
+    include: CompiledRules,
+}
+
+impl RuleSet {
+    pub fn new(config: &Config) -> Self {
+        RuleSet {
+            ignore: CompiledRules::new(config.ignore.clone()),
+            include: CompiledRules::new(config.include.clone()),
+        }
+    }
+
+    // Reading every command line is only worth it when a rule looks at them
+    pub fn uses_cmdlines(&self) -> bool {
+        !self.ignore.cmdlines.is_empty() || !self.include.cmdlines.is_empty()
+    }
+
+    pub fn includes(&self, subject: &Subject) -> bool {
+        self.include.matches(subject)
+    }
+
+    pub fn hides(&self, subject: &Subject) -> bool {
+        self.ignore.matches(subject) && !self.include.matches(subject)
+    }
+}
+
+// Home directory of a user as /etc/passwd has it
+fn passwd_home(uid: u32) -> Option<PathBuf> {
+    let passwd = fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        // name:password:uid:gid:gecos:home:shell
+        let fields: Vec<&str> = line.split(':').collect();
+        (fields.len() >= 6 && fields[2].parse() == Ok(uid)).then(|| PathBuf::from(fields[5]))
+    })
+}
+
+pub(crate) fn config_path() -> Option<PathBuf> {
+    // pkexec runs us as root with a clean environment, the rules are those of the user who asked
+    if let Some(uid) = std::env::var("PKEXEC_UID").ok().and_then(|u| u.parse().ok()) {
+        return Some(passwd_home(uid)?.join(".config").join("ppkiller").join("config.toml"));
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("ppkiller").join("config.toml"))
+}
+
+// Keys of `overlay` replace those of `base`, tables are merged key by key
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+// Keys missing from the file keep their default, so `[ignore] ports = [...]` leaves
+// the ignored processes and users alone
+fn parse(content: &str) -> Result<Config, String> {
+    let file: toml::Table = content.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    let mut table = toml::Table::try_from(Config::default()).map_err(|e| e.to_string())?;
+    merge(&mut table, file);
+    let config: Config = table.try_into().map_err(|e: toml::de::Error| e.to_string())?;
+    config.validate()?;
+    Ok(config)
+}
+
+// Read on every scan so edits apply right away; a broken file falls back to the defaults
+pub(crate) fn load() -> Config {
+    let Some(content) = config_path().and_then(|p| fs::read_to_string(p).ok()) else {
+        return Config::default();
+    };
+    parse(&content).unwrap_or_else(|err| {
+        log::warn!("Ignoring config.toml: {}", err);
+        Config::default()
+    })
+}
+
+pub(crate) fn save(config: &Config) -> Result<PathBuf, String> {
+    config.validate()?;
+    let path = config_path().ok_or("Could not find HOME dir")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let content = toml::to_string_pretty(config).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+fn lookup<'a>(table: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let (first, rest) = match key.split_once('.') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (key, None),
+    };
+    let value = table.get(first)?;
+    match rest {
+        Some(rest) => lookup(value.as_table()?, rest),
+        None => Some(value),
+    }
+}
+
+// `ppkiller config get ignore.ports`; no key prints the whole file
+pub(crate) fn get_impl(key: Option<&str>) -> Result<String, String> {
+    let config = load();
+    let table = toml::Table::try_from(&config).map_err(|e| e.to_string())?;
+    let Some(key) = key else {
+        return toml::to_string_pretty(&config).map_err(|e| e.to_string());
+    };
+    match lookup(&table, key) {
+        Some(toml::Value::Table(t)) => toml::to_string_pretty(t).map_err(|e| e.to_string()),
+        Some(value) => Ok(value.to_string()),
+        // Unset optional keys
+        None if key == "source" => Ok(String::new()),
+        None => Err(format!("Unknown config key '{}'", key)),
+    }
+}
+
+// A TOML value ("[80, \"8000-8100\"]", "\"netlink\"", "true"), or a bare word / comma-separated list
+fn parse_value(value: &str, list: bool) -> toml::Value {
+    if let Ok(table) = format!("v = {}", value).parse::<toml::Table>() {
+        if let Some(parsed) = table.get("v") {
+            if !list || parsed.is_array() {
+                return parsed.clone();
+            }
+        }
+    }
+    if !list {
+        return toml::Value::String(value.to_string());
+    }
+    let items = value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(|item| match item.parse::<i64>() {
+        Ok(n) => toml::Value::Integer(n),
+        Err(_) => toml::Value::String(item.to_string()),
+    });
+    toml::Value::Array(items.collect())
+}
+
+// `ppkiller config set ignore.ports 53,139,8000-8100`
+pub(crate) fn set_impl(key: &str, value: &str) -> Result<PathBuf, String> {
+    let mut table = toml::Table::try_from(load()).map_err(|e| e.to_string())?;
+    let defaults = toml::Table::try_from(Config::default()).map_err(|e| e.to_string())?;
+    let list = lookup(&defaults, key).map(|v| v.is_array()).unwrap_or(false);
+    if lookup(&defaults, key).is_none() && key != "source" {
+        return Err(format!("Unknown config key '{}'", key));
+    }
+
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let last = parts.pop().ok_or("Empty config key")?;
+    let mut target = &mut table;
+    for part in parts {
+        target = target
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| format!("'{}' is not a table", part))?;
+    }
+    target.insert(last.to_string(), parse_value(value, list));
+
+    let config: Config = table.try_into().map_err(|e: toml::de::Error| format!("{}: {}", key, e))?;
+    save(&config)
+}
+
+// Opens the file in $VISUAL/$EDITOR, writing the defaults first if there is none yet
+pub(crate) fn edit_impl() -> Result<PathBuf, String> {
+    let path = config_path().ok_or("Could not find HOME dir")?;
+    if !path.exists() {
+        save(&Config::default())?;
+    }
+    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status()
+        .map_err(|e| format!("Failed to execute {}: {}", editor, e))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", editor, status));
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse(&content).map_err(|e| format!("{} is not valid, the defaults are used until it is fixed: {}", path.display(), e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_without_wildcards_is_exact() {
+        assert!(glob_match("sshd", "sshd"));
+        assert!(!glob_match("sshd", "sshd-session"));
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob_match("python*", "python3"));
+        assert!(glob_match("python*", "python"));
+        assert!(glob_match("*-daemon", "avahi-daemon"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "acb"));
+        // Prefix and suffix may not overlap
+        assert!(!glob_match("ab*ba", "aba"));
+    }
+}
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, OnceLock};
+
+use crate::{get_ports_impl, netns, parse_proc_net, NetEntry, PortInfo};
+
+// TCP states from include/net/tcp_states.h, as printed in /proc/net/tcp
+const TCP_ESTABLISHED: &str = "01";
+const TCP_TIME_WAIT: &str = "06";
+const TCP_CLOSE_WAIT: &str = "08";
+
+// How many CLOSE_WAIT samples are kept per listener
+const HISTORY_LEN: usize = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PeerInfo {
+    pub address: String,
+    pub connections: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConnectionSummary {
+    pub port: String,
+    pub bind_address: String,
+    pub pid: Option<i32>,
+    pub process_name: Option<String>,
+    pub established: usize,
+    pub time_wait: usize,
+    pub close_wait: usize,
+    pub peers: Vec<PeerInfo>,
+    // CLOSE_WAIT kept rising over the last samples, usually a server that never closes its sockets
+    pub close_wait_growing: bool,
+}
+
+// CLOSE_WAIT counts from previous scans, keyed by "netns/address:port"
+fn close_wait_history() -> &'static Mutex<HashMap<String, Vec<usize>>> {
+    static HISTORY: OnceLock<Mutex<HashMap<String, Vec<usize>>>> = OnceLock::new();
+    HISTORY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// IPv4 clients of a dual-stack listener show up as ::ffff:a.b.c.d in tcp6
+fn canonical(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(addr),
+        v4 => v4,
+    }
+}
+
+// The listener a connected socket was accepted on: the one bound to its exact address,
+// otherwise the wildcard of the same family. IPv4 clients only reach an IPv6 listener
+// as ::ffff:a.b.c.d, and only through a dual-stack [::]
+fn owner_of(listeners: &[PortInfo], netns: &str, entry: &NetEntry) -> Option<usize> {
+    let port = entry.local_port.to_string();
+    let candidates = || {
+        listeners
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| l.netns == netns && l.port == port)
+            .filter_map(|(i, l)| Some((i, l.bind_address.parse::<IpAddr>().ok()?)))
+    };
+    candidates()
+        .find(|(_, addr)| *addr == entry.local_addr)
+        .or_else(|| candidates().find(|(_, addr)| addr.is_unspecified() && addr.is_ipv4() == entry.local_addr.is_ipv4()))
+        .map(|(i, _)| i)
+}
+
+// Update the history for `key` and report whether CLOSE_WAIT is growing
+fn record_close_wait(key: &str, count: usize) -> bool {
+    let mut history = close_wait_history().lock().unwrap_or_else(|e| e.into_inner());
+    let samples = history.entry(key.to_string()).or_default();
+    samples.push(count);
+    if samples.len() > HISTORY_LEN {
+        samples.remove(0);
+    }
+
+    samples.len() >= 2
+        && samples.windows(2).all(|w| w[1] >= w[0])
+        && samples.last() > samples.first()
+}
+
+pub(crate) fn get_connections_impl() -> Vec<ConnectionSummary> {
+    let listeners: Vec<PortInfo> = get_ports_impl()
+        .into_iter()
+        .filter(|p| p.protocol == "tcp")
+        .collect();
+
+    let mut connected: Vec<(String, NetEntry)> = Vec::new();
+    for ns in netns::network_namespaces() {
+        for file in ["tcp", "tcp6"] {
+            let path = format!("{}/{}", ns.net_dir, file);
+            connected.extend(parse_proc_net(&path).into_iter().map(|e| (ns.id.clone(), e)));
+        }
+    }
+    connected.retain(|(_, e)| [TCP_ESTABLISHED, TCP_TIME_WAIT, TCP_CLOSE_WAIT].contains(&e.state.as_str()));
+
+    // (established, time_wait, close_wait, peers) per listener, every connection counted once
+    let mut counts: Vec<(usize, usize, usize, HashMap<String, usize>)> = vec![Default::default(); listeners.len()];
+    for (ns, entry) in &connected {
+        let Some(i) = owner_of(&listeners, ns, entry) else { continue; };
+        let (established, time_wait, close_wait, peers) = &mut counts[i];
+        match entry.state.as_str() {
+            TCP_ESTABLISHED => *established += 1,
+            TCP_TIME_WAIT => *time_wait += 1,
+            _ => *close_wait += 1,
+        }
+        *peers.entry(canonical(entry.remote_addr).to_string()).or_default() += 1;
+    }
+
+    let mut summaries = Vec::new();
+
+    for (listener, (established, time_wait, close_wait, peers)) in listeners.into_iter().zip(counts) {
+        let close_wait_growing = record_close_wait(&format!("{}/{}", listener.netns, listener.address()), close_wait);
+
+        let mut peers: Vec<PeerInfo> = peers
+            .into_iter()
+            .map(|(address, connections)| PeerInfo { address, connections })
+            .collect();
+        peers.sort_by(|a, b| b.connections.cmp(&a.connections).then(a.address.cmp(&b.address)));
+
+        summaries.push(ConnectionSummary {
+            port: listener.port,
+            bind_address: listener.bind_address,
+            pid: listener.pid,
+            process_name: listener.process_name,
+            established,
+            time_wait,
+            close_wait,
+            peers,
+            close_wait_growing,
+        });
+    }
+
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listener(netns: &str, bind_address: &str) -> PortInfo {
+        let family = if bind_address.contains(':') { "ipv6" } else { "ipv4" };
+        PortInfo { port: "8080".into(), protocol: "tcp".into(), bind_address: bind_address.into(), family: family.into(), netns: netns.into(), ..Default::default() }
+    }
+
+    fn connection(local: &str) -> NetEntry {
+        NetEntry {
+            local_addr: local.parse().unwrap(),
+            local_port: 8080,
+            remote_addr: "10.0.0.9".parse().unwrap(),
+            state: TCP_ESTABLISHED.into(),
+            inode: 0,
+            rx_queue: 0,
+        }
+    }
+
+    #[test]
+    fn exact_listener_wins_over_wildcard() {
+        let listeners = [listener("1", "0.0.0.0"), listener("1", "127.0.0.1")];
+        assert_eq!(owner_of(&listeners, "1", &connection("127.0.0.1")), Some(1));
+        assert_eq!(owner_of(&listeners, "1", &connection("192.168.1.2")), Some(0));
+    }
+
+    #[test]
+    fn wildcards_only_take_their_own_family() {
+        let listeners = [listener("1", "0.0.0.0"), listener("1", "::")];
+        assert_eq!(owner_of(&listeners, "1", &connection("192.168.1.2")), Some(0));
+        assert_eq!(owner_of(&listeners, "1", &connection("::1")), Some(1));
+        // IPv4 client of the dual-stack socket, listed in tcp6
+        assert_eq!(owner_of(&listeners, "1", &connection("::ffff:192.168.1.2")), Some(1));
+        assert_eq!(owner_of(&listeners[..1], "1", &connection("::1")), None);
+    }
+
+    #[test]
+    fn other_namespaces_are_not_counted() {
+        let listeners = [listener("1", "0.0.0.0")];
+        assert_eq!(owner_of(&listeners, "2", &connection("127.0.0.1")), None);
+    }
+}
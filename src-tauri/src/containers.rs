@@ -0,0 +1,300 @@
+use regex::Regex;
+use serde_json::Value;
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::proc_root::{self, proc_path};
+
+// Helpers that forward published ports into a container; the port is theirs only on paper
+const PROXY_PROCESSES: [&str; 6] = ["docker-proxy", "rootlessport", "rootlessport-child", "conmon", "slirp4netns", "pasta"];
+
+const API_TIMEOUT: Duration = Duration::from_millis(500);
+// Seconds a container gets to exit on SIGTERM, the engine answers the stop call only afterwards
+const STOP_GRACE: u64 = 10;
+const STOP_TIMEOUT: Duration = Duration::from_secs(STOP_GRACE + 5);
+
+#[derive(Debug, Clone)]
+pub(crate) struct Container {
+    pub id: String,
+    pub name: String,
+    pub compose_project: Option<String>,
+    // (public port, "tcp"/"udp")
+    pub published: Vec<(u16, String)>,
+}
+
+// Docker and Podman API sockets, most specific first
+fn api_sockets() -> Vec<PathBuf> {
+    let mut sockets = Vec::new();
+    for var in ["DOCKER_HOST", "CONTAINER_HOST"] {
+        if let Ok(host) = std::env::var(var) {
+            if let Some(path) = host.strip_prefix("unix://") {
+                sockets.push(PathBuf::from(path));
+            }
+        }
+    }
+    if let Ok(runtime_dir) = std::env::var("XDG_RUNTIME_DIR") {
+        sockets.push(PathBuf::from(&runtime_dir).join("docker.sock"));
+        sockets.push(PathBuf::from(&runtime_dir).join("podman/podman.sock"));
+    }
+    sockets.push(PathBuf::from("/var/run/docker.sock"));
+    sockets.push(PathBuf::from("/run/podman/podman.sock"));
+    sockets.dedup();
+    sockets.into_iter().filter(|s| s.exists()).collect()
+}
+
+// Minimal HTTP/1.0 client over the API socket; returns (status, body)
+fn api_request(socket: &PathBuf, method: &str, path: &str, timeout: Duration) -> io::Result<(u16, String)> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(API_TIMEOUT))?;
+
+    let request = format!("{} {} HTTP/1.0\r\nHost: localhost\r\nContent-Length: 0\r\n\r\n", method, path);
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Invalid response from container API"))?;
+    Ok((status, body.to_string()))
+}
+
+fn parse_containers(body: &str) -> Vec<Container> {
+    let Ok(Value::Array(items)) = serde_json::from_str::<Value>(body) else { return Vec::new(); };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let id = item["Id"].as_str()?.to_string();
+            let name = item["Names"][0].as_str().unwrap_or(&id).trim_start_matches('/').to_string();
+            let labels = &item["Labels"];
+            let compose_project = labels["com.docker.compose.project"]
+                .as_str()
+                .or_else(|| labels["io.podman.compose.project"].as_str())
+                .map(|s| s.to_string());
+            let published = item["Ports"]
+                .as_array()
+                .map(|ports| {
+                    ports
+                        .iter()
+                        .filter_map(|p| {
+                            let public = p["PublicPort"].as_u64()? as u16;
+                            Some((public, p["Type"].as_str().unwrap_or("tcp").to_string()))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(Container { id, name, compose_project, published })
+        })
+        .collect()
+}
+
+// ".../docker-<id>.scope", ".../libpod-<id>.scope", ".../docker/<id>"
+fn container_id_from_cgroup(content: &str) -> Option<String> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"(?:docker|libpod|crio|cri-containerd)[-/]([0-9a-f]{64})").unwrap());
+    re.captures(content).map(|caps| caps[1].to_string())
+}
+
+// Container ID from /proc/[pid]/cgroup
+pub(crate) fn container_id_for_pid(pid: i32) -> Option<String> {
+    let content = fs::read_to_string(proc_path(&format!("{}/cgroup", pid))).ok()?;
+    container_id_from_cgroup(&content)
+}
+
+// Looks containers up lazily, so hosts without Docker/Podman never touch the API
+pub(crate) struct ContainerResolver {
+    sockets: Vec<PathBuf>,
+    containers: Option<Vec<Container>>,
+}
+
+impl ContainerResolver {
+    // A capture replays without the API, containers are only known by their cgroup
+    pub fn new() -> Self {
+        Self::with_sockets(if proc_root::is_live() { api_sockets() } else { Vec::new() })
+    }
+
+    fn with_sockets(sockets: Vec<PathBuf>) -> Self {
+        ContainerResolver { sockets, containers: None }
+    }
+
+    fn containers(&mut self) -> &[Container] {
+        let sockets = &self.sockets;
+        self.containers.get_or_insert_with(|| {
+            let mut all = Vec::new();
+            for socket in sockets {
+                if let Ok((200, body)) = api_request(socket, "GET", "/containers/json", API_TIMEOUT) {
+                    all.extend(parse_containers(&body));
+                }
+            }
+            all
+        })
+    }
+
+    // Find the container behind a listener: by the owner's cgroup, or for proxy
+    // processes and sockets without a visible owner, by the published port
+    pub fn resolve(&mut self, pid: Option<i32>, process_name: Option<&str>, protocol: &str, port: u16) -> Option<Container> {
+        if let Some(id) = pid.and_then(container_id_for_pid) {
+            let known = self.containers().iter().find(|c| c.id == id).cloned();
+            return Some(known.unwrap_or(Container {
+                name: id[..12].to_string(),
+                id,
+                compose_project: None,
+                published: Vec::new(),
+            }));
+        }
+
+        let is_proxy = process_name.map(|n| PROXY_PROCESSES.contains(&n)).unwrap_or(true);
+        if !is_proxy {
+            return None;
+        }
+        self.containers()
+            .iter()
+            .find(|c| c.published.iter().any(|(p, t)| *p == port && t == protocol))
+            .cloned()
+    }
+}
+
+// None when no engine knows the container or no socket answered
+fn stop_through_api(sockets: &[PathBuf], container_id: &str, timeout: Duration) -> Option<Result<String, String>> {
+    let path = format!("/containers/{}/stop?t={}", container_id, STOP_GRACE);
+    for socket in sockets {
+        match api_request(socket, "POST", &path, timeout) {
+            // 204 stopped, 304 already stopped
+            Ok((204, _)) | Ok((304, _)) => return Some(Ok(format!("Container {} stopped", container_id))),
+            // Not on this engine, try the next socket
+            Ok((404, _)) => continue,
+            Ok((status, body)) => return Some(Err(format!("Container API returned {}: {}", status, body.trim()))),
+            // The engine took the request and is still waiting for the container to exit
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Some(Ok(format!("Container {} is stopping", container_id)));
+            }
+            Err(_) => continue,
+        }
+    }
+    None
+}
+
+pub(crate) fn stop_container_impl(container_id: &str) -> Result<String, String> {
+    // Only hex IDs and compose-style names, this ends up in a URL path
+    if container_id.is_empty() || !container_id.chars().all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c)) {
+        return Err(format!("Invalid container id '{}'", container_id));
+    }
+
+    if let Some(result) = stop_through_api(&api_sockets(), container_id, STOP_TIMEOUT) {
+        return result;
+    }
+
+    // Fallback to the CLIs, e.g. when the API socket is not readable
+    for cli in ["docker", "podman"] {
+        if let Ok(status) = Command::new(cli).args(["stop", container_id]).status() {
+            if status.success() {
+                return Ok(format!("Container {} stopped", container_id));
+            }
+        }
+    }
+
+    Err(format!("Could not stop container '{}'", container_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixListener;
+
+    const CONTAINERS: &str = r#"[{
+        "Id": "4f1c9b7e2d3a4f1c9b7e2d3a4f1c9b7e2d3a4f1c9b7e2d3a4f1c9b7e2d3a4f1c",
+        "Names": ["/shop-db-1"],
+        "Labels": {"com.docker.compose.project": "shop"},
+        "Ports": [{"PrivatePort": 5432, "PublicPort": 15432, "Type": "tcp"}, {"PrivatePort": 9000, "Type": "tcp"}]
+    }]"#;
+
+    // Answers every request with the container list and reports the request lines it saw
+    fn fake_api(socket: &PathBuf) -> std::sync::mpsc::Receiver<String> {
+        fake_api_with(socket, Duration::ZERO, "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n".to_string() + CONTAINERS)
+    }
+
+    // Waits `delay` before it sends `response`, like an engine waiting for a container to exit
+    fn fake_api_with(socket: &PathBuf, delay: Duration, response: String) -> std::sync::mpsc::Receiver<String> {
+        let _ = fs::remove_file(socket);
+        let listener = UnixListener::bind(socket).unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let _ = reader.read_line(&mut request_line);
+                let mut line = String::new();
+                while reader.read_line(&mut line).map(|n| n > 2).unwrap_or(false) {
+                    line.clear();
+                }
+                let _ = tx.send(request_line.trim_end().to_string());
+                std::thread::sleep(delay);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        rx
+    }
+
+    #[test]
+    fn resolves_published_ports_through_the_api() {
+        let dir = std::env::temp_dir().join(format!("ppkiller-containers-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("docker.sock");
+        let requests = fake_api(&socket);
+
+        let mut resolver = ContainerResolver::with_sockets(vec![socket.clone()]);
+        let container = resolver.resolve(None, Some("docker-proxy"), "tcp", 15432).expect("container for 15432");
+        assert_eq!(container.name, "shop-db-1");
+        assert_eq!(container.compose_project.as_deref(), Some("shop"));
+        // Not published, and not a proxy process
+        assert!(resolver.resolve(None, Some("docker-proxy"), "udp", 15432).is_none());
+        assert!(resolver.resolve(None, Some("postgres"), "tcp", 15432).is_none());
+        // The list is fetched once per resolver
+        assert_eq!(requests.recv().unwrap(), "GET /containers/json HTTP/1.0");
+        assert!(requests.try_recv().is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_slow_stop_is_in_progress_not_failed() {
+        let dir = std::env::temp_dir().join(format!("ppkiller-stop-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let id = "4f1c9b7e2d3a";
+
+        let quick = dir.join("quick.sock");
+        let requests = fake_api_with(&quick, Duration::ZERO, "HTTP/1.0 204 No Content\r\n\r\n".into());
+        assert_eq!(stop_through_api(&[quick], id, Duration::from_millis(500)), Some(Ok(format!("Container {} stopped", id))));
+        assert_eq!(requests.recv().unwrap(), format!("POST /containers/{}/stop?t={} HTTP/1.0", id, STOP_GRACE));
+
+        let slow = dir.join("slow.sock");
+        let _requests = fake_api_with(&slow, Duration::from_secs(2), "HTTP/1.0 204 No Content\r\n\r\n".into());
+        assert_eq!(stop_through_api(&[slow], id, Duration::from_millis(200)), Some(Ok(format!("Container {} is stopping", id))));
+
+        let missing = dir.join("missing.sock");
+        assert_eq!(stop_through_api(&[missing], id, Duration::from_millis(200)), None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn container_ids_from_cgroup_paths() {
+        let id = "4f1c9b7e2d3a4f1c9b7e2d3a4f1c9b7e2d3a4f1c9b7e2d3a4f1c9b7e2d3a4f1c";
+        let docker = format!("0::/system.slice/docker-{}.scope\n", id);
+        let podman = format!("0::/user.slice/user-1000.slice/user@1000.service/user.slice/libpod-{}.scope/container\n", id);
+        assert_eq!(container_id_from_cgroup(&docker).as_deref(), Some(id));
+        assert_eq!(container_id_from_cgroup(&podman).as_deref(), Some(id));
+        assert_eq!(container_id_from_cgroup("0::/user.slice/user-1000.slice/session-2.scope\n"), None);
+    }
+}
@@ -0,0 +1,153 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::proc_root::read_process;
+use crate::{get_ports_impl, PortInfo};
+
+// What changed between two scans
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "event")]
+pub enum PortEvent {
+    PortOpened { port: PortInfo },
+    PortClosed { port: PortInfo },
+    // Same address and port, new main owner: the service restarted
+    OwnerChanged { port: PortInfo, old_pid: Option<i32>, new_pid: Option<i32> },
+    // A process that held a port is gone
+    ProcessExited { pid: i32, process_name: Option<String> },
+}
+
+fn key(port: &PortInfo) -> String {
+    format!("{}/{}/{}/{}", port.netns, port.protocol, port.bind_address, port.port)
+}
+
+// A reused PID belongs to another process, so the start time has to match too
+fn is_running(pid: i32, start_ticks: Option<u64>) -> bool {
+    match read_process(pid) {
+        Some(process) => start_ticks.map_or(true, |ticks| ticks == process.start_ticks),
+        None => false,
+    }
+}
+
+// Events come out in key order (then PID order), not in HashMap order
+fn diff(old: &HashMap<String, PortInfo>, new: &HashMap<String, PortInfo>, running: impl Fn(i32, Option<u64>) -> bool) -> Vec<PortEvent> {
+    let mut events = Vec::new();
+
+    let mut opened: Vec<_> = new.iter().collect();
+    opened.sort_by(|a, b| a.0.cmp(b.0));
+    for (k, port) in opened {
+        match old.get(k) {
+            None => events.push(PortEvent::PortOpened { port: port.clone() }),
+            Some(previous) if previous.pid != port.pid => events.push(PortEvent::OwnerChanged {
+                port: port.clone(),
+                old_pid: previous.pid,
+                new_pid: port.pid,
+            }),
+            _ => {}
+        }
+    }
+    let mut closed: Vec<_> = old.iter().filter(|(k, _)| !new.contains_key(*k)).collect();
+    closed.sort_by(|a, b| a.0.cmp(b.0));
+    for (_, port) in closed {
+        events.push(PortEvent::PortClosed { port: port.clone() });
+    }
+
+    let mut owners: Vec<_> = old.values().flat_map(|p| p.owners.iter()).collect();
+    owners.sort_by_key(|owner| owner.pid);
+    let mut seen = HashSet::new();
+    for owner in owners {
+        if seen.insert(owner.pid) && !running(owner.pid, owner.start_ticks) {
+            events.push(PortEvent::ProcessExited { pid: owner.pid, process_name: owner.process_name.clone() });
+        }
+    }
+
+    events
+}
+
+// Keeps the last scan and reports what changed on every poll
+pub struct PortWatcher {
+    previous: HashMap<String, PortInfo>,
+}
+
+impl PortWatcher {
+    // The first scan is the baseline, it produces no events
+    pub fn new() -> Self {
+        PortWatcher { previous: Self::scan() }
+    }
+
+    fn scan() -> HashMap<String, PortInfo> {
+        get_ports_impl().into_iter().map(|p| (key(&p), p)).collect()
+    }
+
+    pub fn poll(&mut self) -> Vec<PortEvent> {
+        let current = Self::scan();
+        let events = diff(&self.previous, &current, is_running);
+        self.previous = current;
+        events
+    }
+}
+
+impl Default for PortWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SocketOwner;
+
+    fn port(number: &str, pid: i32) -> PortInfo {
+        let owner = SocketOwner { pid, process_name: Some("node".into()), role: "owner".into(), start_ticks: Some(100) };
+        PortInfo { port: number.into(), protocol: "tcp".into(), bind_address: "127.0.0.1".into(), netns: "host".into(), pid: Some(pid), owners: vec![owner], ..Default::default() }
+    }
+
+    fn scan(ports: &[PortInfo]) -> HashMap<String, PortInfo> {
+        ports.iter().map(|p| (key(p), p.clone())).collect()
+    }
+
+    fn names(events: &[PortEvent]) -> Vec<String> {
+        events.iter().map(|event| match event {
+            PortEvent::PortOpened { port } => format!("opened {}", port.port),
+            PortEvent::PortClosed { port } => format!("closed {}", port.port),
+            PortEvent::OwnerChanged { port, old_pid, new_pid } => format!("restart {} {:?}->{:?}", port.port, old_pid, new_pid),
+            PortEvent::ProcessExited { pid, .. } => format!("exited {}", pid),
+        }).collect()
+    }
+
+    #[test]
+    fn unchanged_scans_produce_no_events() {
+        let ports = scan(&[port("3000", 10), port("5432", 20)]);
+        assert!(diff(&ports, &ports, |_, _| true).is_empty());
+    }
+
+    #[test]
+    fn events_come_out_in_key_order() {
+        let old = scan(&[port("5000", 50), port("3000", 30), port("4000", 40)]);
+        let new = scan(&[port("9000", 90), port("3000", 31), port("8000", 80)]);
+        let events = diff(&old, &new, |pid, _| pid != 40 && pid != 50);
+        assert_eq!(names(&events), [
+            "restart 3000 Some(30)->Some(31)",
+            "opened 8000",
+            "opened 9000",
+            "closed 4000",
+            "closed 5000",
+            "exited 40",
+            "exited 50",
+        ]);
+    }
+
+    #[test]
+    fn an_owner_is_reported_once_across_ports() {
+        let old = scan(&[port("3000", 10), port("3001", 10)]);
+        let events = diff(&old, &HashMap::new(), |_, _| false);
+        assert_eq!(names(&events), ["closed 3000", "closed 3001", "exited 10"]);
+    }
+
+    #[test]
+    fn a_reused_pid_counts_as_exited() {
+        let old = scan(&[port("3000", 10)]);
+        let events = diff(&old, &HashMap::new(), |_, ticks| ticks == Some(200));
+        assert_eq!(names(&events), ["closed 3000", "exited 10"]);
+    }
+}
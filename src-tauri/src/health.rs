@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::proc_root::proc_path;
+use crate::{parse_proc_net, probe, proc_root, PortInfo};
+
+const TIMEOUT: Duration = Duration::from_millis(1000);
+// Slower than this to connect or answer is worth a look
+const SLOW_MS: u64 = 250;
+
+// Results of the GUI's background checks by listener, so get_ports does not connect itself
+static LAST: OnceLock<Mutex<HashMap<String, PortHealth>>> = OnceLock::new();
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    Slow,
+    // Listening but not accepting or not answering, usually deadlocked
+    Hung,
+}
+
+impl HealthStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Slow => "slow",
+            HealthStatus::Hung => "hung",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PortHealth {
+    pub status: HealthStatus,
+    pub connect_ms: Option<u64>,
+    // Time to the first byte of the protocol ping, when there is a safe one
+    pub response_ms: Option<u64>,
+    // Connections the process has not accepted yet
+    pub accept_queue: Option<u32>,
+    pub reason: Option<String>,
+}
+
+fn millis(since: Instant) -> u64 {
+    since.elapsed().as_millis() as u64
+}
+
+fn verdict(status: HealthStatus, reason: Option<String>) -> PortHealth {
+    PortHealth { status, connect_ms: None, response_ms: None, accept_queue: None, reason }
+}
+
+// Current accept queue of the listener, straight from /proc/net/tcp[6]
+fn accept_queue(port: &PortInfo) -> Option<u32> {
+    let file = if port.family == "ipv6" { "net/tcp6" } else { "net/tcp" };
+    parse_proc_net(&proc_path(file))
+        .into_iter()
+        .find(|e| e.state == "0A" && e.local_port.to_string() == port.port && e.local_addr.to_string() == port.bind_address)
+        .map(|e| e.rx_queue)
+}
+
+// Without a protocol to ping, wait for our connection to leave the accept queue: back to the
+// length it had before we connected, older connections may be waiting there for good
+fn wait_for_accept(port: &PortInfo, before: u32) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT {
+        if accept_queue(port).unwrap_or(0) <= before {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+// What the scan's accept queue and backlog say, without connecting
+fn queue_verdict(port: &PortInfo) -> PortHealth {
+    let queue = port.socket_options.accept_queue;
+    let backlog = port.socket_options.backlog.filter(|b| *b > 0);
+
+    // With a full queue the kernel drops new SYNs, nothing has been accepted for a while
+    if let (Some(queue), Some(backlog)) = (queue, backlog) {
+        if queue >= backlog {
+            let mut health = verdict(HealthStatus::Hung, Some(format!("accept queue full ({}/{})", queue, backlog)));
+            health.accept_queue = Some(queue);
+            return health;
+        }
+    }
+
+    // Connections that were never accepted stay queued, even after the client gave up,
+    // so a listener that stopped calling accept() shows up here on the next scan
+    let waiting = queue.filter(|q| *q > 0).map(|q| format!("{} connection(s) waiting to be accepted", q));
+    let mut health = verdict(if waiting.is_some() { HealthStatus::Slow } else { HealthStatus::Healthy }, waiting);
+    health.accept_queue = queue;
+    health
+}
+
+fn check_port(port: &PortInfo) -> PortHealth {
+    let mut health = queue_verdict(port);
+    if health.status == HealthStatus::Hung {
+        return health;
+    }
+
+    // Other namespaces are not reachable over our loopback, the queue is all we know
+    let Some(addr) = probe::target(port).filter(|_| !port.namespaced && proc_root::is_live()) else {
+        return health;
+    };
+
+    let before = accept_queue(port).unwrap_or(0);
+    let start = Instant::now();
+    let mut stream = match TcpStream::connect_timeout(&addr, TIMEOUT) {
+        Ok(stream) => stream,
+        Err(e) if e.kind() == ErrorKind::TimedOut => {
+            health.status = HealthStatus::Hung;
+            health.reason = Some(format!("connect timed out after {} ms", TIMEOUT.as_millis()));
+            return health;
+        }
+        // Closed since the scan
+        Err(e) => {
+            health.reason = Some(e.to_string());
+            return health;
+        }
+    };
+    let connect_ms = millis(start);
+    health.connect_ms = Some(connect_ms);
+    if connect_ms > SLOW_MS && health.status == HealthStatus::Healthy {
+        health.status = HealthStatus::Slow;
+        health.reason = Some(format!("connect took {} ms", connect_ms));
+    }
+
+    let Some(request) = port.protocol_guess.as_deref().and_then(probe::ping_request) else {
+        if !wait_for_accept(port, before) {
+            health.status = HealthStatus::Hung;
+            health.reason = Some(format!("connections are not accepted within {} ms", TIMEOUT.as_millis()));
+        }
+        return health;
+    };
+    let _ = stream.set_read_timeout(Some(TIMEOUT));
+    let _ = stream.set_write_timeout(Some(TIMEOUT));
+    let start = Instant::now();
+    if !request.is_empty() && stream.write_all(&request).is_err() {
+        return health;
+    }
+
+    let mut buf = [0u8; 256];
+    match stream.read(&mut buf) {
+        // A reply or a clean close both mean the process is serving connections
+        Ok(_) => {
+            let response_ms = millis(start);
+            health.response_ms = Some(response_ms);
+            if response_ms > SLOW_MS && health.status == HealthStatus::Healthy {
+                health.status = HealthStatus::Slow;
+                health.reason = Some(format!("{} answered in {} ms", port.protocol_guess.as_deref().unwrap_or("server"), response_ms));
+            }
+        }
+        Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+            health.status = HealthStatus::Hung;
+            health.reason = Some(format!(
+                "accepts connections but no {} response within {} ms",
+                port.protocol_guess.as_deref().unwrap_or("protocol"),
+                TIMEOUT.as_millis()
+            ));
+        }
+        Err(_) => {}
+    }
+    health
+}
+
+// Fill in PortInfo.health for TCP listeners, all checked in parallel
+pub(crate) fn check(ports: &mut [PortInfo]) {
+    std::thread::scope(|s| {
+        let handles: Vec<_> = ports
+            .iter_mut()
+            .filter(|p| p.protocol == "tcp")
+            .map(|p| s.spawn(move || p.health = Some(check_port(p))))
+            .collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+    });
+}
+
+// Only the accept queue verdict, for callers that poll too often to connect every time
+pub(crate) fn check_queues(ports: &mut [PortInfo]) {
+    for port in ports.iter_mut().filter(|p| p.protocol == "tcp") {
+        port.health = Some(queue_verdict(port));
+    }
+}
+
+fn key(port: &PortInfo) -> String {
+    format!("{}/{}/{}", port.netns, port.bind_address, port.port)
+}
+
+// Check again and remember the results; true when a listener changed status since the last time
+pub(crate) fn refresh(ports: &mut [PortInfo]) -> bool {
+    check(ports);
+    let mut last = LAST.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap_or_else(|e| e.into_inner());
+    let status = |h: Option<&PortHealth>| h.map(|h| h.status).unwrap_or(HealthStatus::Healthy);
+    let changed = ports.iter().any(|p| status(last.get(&key(p))) != status(p.health.as_ref()));
+    *last = ports.iter().filter_map(|p| Some((key(p), p.health.clone()?))).collect();
+    changed
+}
+
+// Health from the last refresh(), nothing for listeners it has not seen yet
+pub(crate) fn annotate_cached(ports: &mut [PortInfo]) {
+    let Some(last) = LAST.get() else {
+        return;
+    };
+    let last = last.lock().unwrap_or_else(|e| e.into_inner());
+    for port in ports.iter_mut().filter(|p| p.protocol == "tcp") {
+        port.health = last.get(&key(port)).cloned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SocketOptions;
+
+    // Namespaced, so check() goes by the queue and never connects
+    fn listener(port: &str, accept_queue: Option<u32>, backlog: Option<u32>) -> PortInfo {
+        PortInfo {
+            port: port.into(),
+            protocol: "tcp".into(),
+            bind_address: "10.0.0.2".into(),
+            netns: "4026532999".into(),
+            namespaced: true,
+            socket_options: SocketOptions { accept_queue, backlog, ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_full_accept_queue_is_hung() {
+        let health = queue_verdict(&listener("8080", Some(128), Some(128)));
+        assert_eq!(health.status, HealthStatus::Hung);
+        assert_eq!(health.accept_queue, Some(128));
+        assert_eq!(health.reason.as_deref(), Some("accept queue full (128/128)"));
+        assert_eq!(check_port(&listener("8080", Some(130), Some(128))).status, HealthStatus::Hung);
+    }
+
+    #[test]
+    fn waiting_connections_are_slow() {
+        let health = queue_verdict(&listener("8080", Some(3), Some(128)));
+        assert_eq!(health.status, HealthStatus::Slow);
+        assert_eq!(health.reason.as_deref(), Some("3 connection(s) waiting to be accepted"));
+        // Without a known backlog the queue can only say slow
+        assert_eq!(queue_verdict(&listener("8080", Some(500), None)).status, HealthStatus::Slow);
+        assert_eq!(queue_verdict(&listener("8080", Some(5), Some(0))).status, HealthStatus::Slow);
+    }
+
+    #[test]
+    fn an_empty_or_unknown_queue_is_healthy() {
+        assert_eq!(queue_verdict(&listener("8080", Some(0), Some(128))).status, HealthStatus::Healthy);
+        let health = queue_verdict(&listener("8080", None, None));
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert_eq!(health.reason, None);
+    }
+
+    #[test]
+    fn queue_checks_leave_udp_alone() {
+        let mut ports = [listener("8080", Some(4), Some(4)), PortInfo { protocol: "udp".into(), ..listener("53", None, None) }];
+        check_queues(&mut ports);
+        assert_eq!(ports[0].health.as_ref().map(|h| h.status), Some(HealthStatus::Hung));
+        assert_eq!(ports[1].health, None);
+    }
+
+    // The only test touching LAST, the cache is shared by the whole process
+    #[test]
+    fn refresh_reports_changes_and_annotate_reuses_them() {
+        let healthy = listener("9001", Some(0), Some(128));
+        let hung = listener("9002", Some(128), Some(128));
+
+        assert!(refresh(&mut [healthy.clone(), hung.clone()]));
+        assert!(!refresh(&mut [healthy.clone(), hung.clone()]));
+
+        let mut ports = [healthy.clone(), hung.clone(), listener("9003", Some(0), Some(128))];
+        annotate_cached(&mut ports);
+        assert_eq!(ports[0].health.as_ref().map(|h| h.status), Some(HealthStatus::Healthy));
+        assert_eq!(ports[1].health.as_ref().map(|h| h.status), Some(HealthStatus::Hung));
+        assert_eq!(ports[2].health, None);
+
+        // Recovered: a change again, and the cache follows
+        let mut recovered = [healthy, listener("9002", Some(0), Some(128))];
+        assert!(refresh(&mut recovered.clone()));
+        annotate_cached(&mut recovered);
+        assert_eq!(recovered[1].health.as_ref().map(|h| h.status), Some(HealthStatus::Healthy));
+    }
+}
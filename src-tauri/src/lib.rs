@@ -2,60 +2,300 @@ use serde::{Deserialize, Serialize};
 use std::process::Command;
 use std::fs;
 use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::path::PathBuf;
-use sysinfo::{Pid, System, ProcessesToUpdate, Users};
+use sysinfo::{System, ProcessesToUpdate};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+mod capture;
+mod classifier;
+mod cmdline;
+mod config;
+mod connections;
+mod containers;
+mod events;
+mod health;
+mod netns;
+mod plan;
+mod probe;
+mod proc_root;
+mod process_tree;
+mod project;
+mod protect;
+mod sources;
+mod systemd;
+mod terminate;
+mod unix_sockets;
+
+pub use classifier::CategoryRule;
+pub use config::{Config, MatchRules, PortSpec};
+pub use connections::{ConnectionSummary, PeerInfo};
+pub use events::{PortEvent, PortWatcher};
+pub use health::{HealthStatus, PortHealth};
+pub use plan::{BulkKill, KillPlan, MatchMode, PlannedKill, ProtectedProcess};
+pub use process_tree::{KillMode, ProcessLink};
+pub use sources::{SocketOptions, SourceKind};
+pub use terminate::{KillOutcome, KillReport, KillResult, Signal, TerminationPolicy};
+pub use unix_sockets::UnixSocketInfo;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct PortInfo {
     pub port: String,
+    pub protocol: String,
+    pub bind_address: String,
+    // "ipv4" or "ipv6"
+    pub family: String,
+    // Inode of the network namespace the socket lives in
+    pub netns: String,
+    // True when the socket is not in the host namespace (containers, sandboxes, unshare -n)
+    pub namespaced: bool,
+    // Main owner, the first of `owners`
     pub pid: Option<i32>,
+    // Kernel comm, at most 15 characters
     pub process_name: Option<String>,
+    // Readable name derived from the command line, e.g. "node vite"
+    pub display_name: Option<String>,
+    pub cmdline: Option<String>,
+    pub cwd: Option<String>,
+    pub exe: Option<String>,
+    // Nearest package.json/Cargo.toml/pyproject.toml/go.mod/.git above the owner's cwd
+    pub project: Option<String>,
+    pub project_root: Option<String>,
+    pub git_branch: Option<String>,
+    // Every process holding the socket (prefork workers, inherited fds)
+    pub owners: Vec<SocketOwner>,
+    // systemd service of the main owner, "system" or "user" manager, and its Restart= policy
+    pub systemd_unit: Option<String>,
+    pub systemd_scope: Option<String>,
+    pub restart_policy: Option<String>,
+    // .socket unit that listens on the port on behalf of systemd_unit (socket activation)
+    pub socket_unit: Option<String>,
+    // When the main owner started, seconds since the epoch
+    pub started_at: Option<u64>,
+    // Start time of the main owner in clock ticks, sent back with kills so a reused PID is refused
+    pub start_ticks: Option<u64>,
+    // Parent chain of the main owner, nearest first, e.g. sh -> npm -> bash
+    pub ancestors: Vec<ProcessLink>,
     pub user: String,
+    // "web", "database", "messaging", "devtools", "system" or a user-defined category
+    pub category: String,
+    // "postgresql", "vite", "http-alt", ... from the built-in table, user rules or /etc/services
+    pub service_name: Option<String>,
+    // What the listener speaks, from a handshake over loopback: "http", "tls", "postgresql", "ssh", ...
+    pub protocol_guess: Option<String>,
+    // Server header, version or banner where the handshake shows one
+    pub protocol_detail: Option<String>,
+    // Result of the last health check, None for UDP and until a check ran
+    pub health: Option<PortHealth>,
+    // Backlog, SO_REUSEPORT, IPV6_V6ONLY where the socket source can see them
+    pub socket_options: SocketOptions,
+    // Docker/Podman container the port really belongs to
+    pub container_id: Option<String>,
+    pub container_name: Option<String>,
+    pub compose_project: Option<String>,
+}
+
+impl PortInfo {
+    // "127.0.0.1:5432" or "[::]:5432"
+    pub fn address(&self) -> String {
+        if self.family == "ipv6" {
+            format!("[{}]:{}", self.bind_address, self.port)
+        } else {
+            format!("{}:{}", self.bind_address, self.port)
+        }
+    }
+
+    // Where a browser can reach the listener, for ports that speak HTTP (or TLS, most likely HTTPS)
+    pub fn url(&self) -> Option<String> {
+        let scheme = match self.protocol_guess.as_deref()? {
+            "http" => "http",
+            "tls" => "https",
+            _ => return None,
+        };
+        let host = match self.bind_address.as_str() {
+            "0.0.0.0" | "::" | "127.0.0.1" | "::1" => "localhost".to_string(),
+            addr if self.family == "ipv6" => format!("[{}]", addr),
+            addr => addr.to_string(),
+        };
+        Some(format!("{}://{}:{}/", scheme, host, self.port))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SocketOwner {
+    pub pid: i32,
+    pub process_name: Option<String>,
+    // "owner", "parent" (other holders were forked from it), "child", "group" (same process group) or "other"
+    pub role: String,
+    pub start_ticks: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProcessInfo {
     pub pid: i32,
     pub name: String,
+    pub display_name: String,
+    pub cmdline: Option<String>,
+    pub cwd: Option<String>,
+    pub exe: Option<String>,
     pub cpu: String,
     pub mem: String,
     pub user: String,
+    pub start_ticks: Option<u64>,
 }
 
-// Helper to read /proc/net/tcp and tcp6
-fn scan_proc_net_tcp(file: &str) -> Vec<(u16, i32)> {
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PortFilter {
+    // "tcp" or "udp", None means both
+    pub protocol: Option<String>,
+    // "host" or "namespaced", None means both
+    pub netns: Option<String>,
+    // Only ports of this category ("web", "database", ...)
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+impl PortFilter {
+    // An unknown value would silently match everything (netns) or nothing (protocol)
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(protocol) = self.protocol.as_deref().filter(|p| !["tcp", "udp"].contains(&p.to_lowercase().as_str())) {
+            return Err(format!("Unknown protocol '{}' (expected tcp or udp)", protocol));
+        }
+        if let Some(netns) = self.netns.as_deref().filter(|n| !["host", "namespaced"].contains(n)) {
+            return Err(format!("Unknown network namespace filter '{}' (expected host or namespaced)", netns));
+        }
+        if let Some(category) = &self.category {
+            // Built-in ones and those of [[category]] rules in config.toml
+            let mut known: Vec<String> = classifier::CATEGORIES.iter().map(|c| c.to_string()).collect();
+            for rule in config::load().categories {
+                if !known.contains(&rule.category) {
+                    known.push(rule.category);
+                }
+            }
+            if !known.iter().any(|c| c.eq_ignore_ascii_case(category)) {
+                return Err(format!("Unknown category '{}' (expected {})", category, known.join(", ")));
+            }
+        }
+        Ok(())
+    }
+
+    fn matches(&self, port: &PortInfo) -> bool {
+        if let Some(protocol) = &self.protocol {
+            if !protocol.eq_ignore_ascii_case(&port.protocol) {
+                return false;
+            }
+        }
+        match self.netns.as_deref() {
+            Some("host") if port.namespaced => return false,
+            Some("namespaced") if !port.namespaced => return false,
+            _ => {}
+        }
+        if let Some(category) = &self.category {
+            if !category.eq_ignore_ascii_case(&port.category) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// One socket row from /proc/net/{tcp,tcp6,udp,udp6}
+#[derive(Debug, Clone)]
+pub(crate) struct NetEntry {
+    pub local_addr: IpAddr,
+    pub local_port: u16,
+    pub remote_addr: IpAddr,
+    pub state: String,
+    pub inode: i32,
+    // For listeners, connections waiting in the accept queue
+    pub rx_queue: u32,
+}
+
+// Decode the hex address procfs prints, e.g. "0100007F" -> 127.0.0.1.
+// Every 32-bit word is printed in host byte order, so IPv6 addresses
+// have to be reassembled word by word.
+fn parse_hex_addr(hex: &str) -> Option<IpAddr> {
+    let words: Vec<u32> = (0..hex.len() / 8)
+        .map(|i| u32::from_str_radix(hex.get(i * 8..i * 8 + 8)?, 16).ok())
+        .collect::<Option<Vec<u32>>>()?;
+
+    match words.len() {
+        1 => Some(IpAddr::V4(Ipv4Addr::from(words[0].to_ne_bytes()))),
+        4 => {
+            let mut bytes = [0u8; 16];
+            for (i, word) in words.iter().enumerate() {
+                bytes[i * 4..i * 4 + 4].copy_from_slice(&word.to_ne_bytes());
+            }
+            Some(IpAddr::V6(Ipv6Addr::from(bytes)))
+        }
+        _ => None,
+    }
+}
+
+// Parse "0100007F:1F90" into address and port
+fn parse_hex_socket(field: &str) -> Option<(IpAddr, u16)> {
+    let (addr, port) = field.split_once(':')?;
+    Some((parse_hex_addr(addr)?, u16::from_str_radix(port, 16).ok()?))
+}
+
+// Shared parser for /proc/net/{tcp,tcp6,udp,udp6}, returns sockets in every state
+pub(crate) fn parse_proc_net(file: &str) -> Vec<NetEntry> {
     let mut results = Vec::new();
     if let Ok(content) = fs::read_to_string(file) {
         for line in content.lines().skip(1) {
             let parts: Vec<&str> = line.split_whitespace().collect();
             if parts.len() < 10 { continue; }
             
-            // State 0A means LISTEN
-            if parts[3] != "0A" { continue; }
-            
-            // Parse port from hex "00000000:1F90" -> 1F90 is port
-            let local_addr = parts[1];
-            if let Some(pos) = local_addr.find(':') {
-                if let Ok(port) = u16::from_str_radix(&local_addr[pos+1..], 16) {
-                    // Inode is at index 9
-                    if let Ok(inode) = parts[9].parse::<i32>() {
-                         results.push((port, inode));
-                    }
-                }
+            let Some((local_addr, local_port)) = parse_hex_socket(parts[1]) else { continue; };
+            let Some((remote_addr, _)) = parse_hex_socket(parts[2]) else { continue; };
+            // "tx_queue:rx_queue" in hex
+            let rx_queue = parts[4].split_once(':').and_then(|(_, rx)| u32::from_str_radix(rx, 16).ok()).unwrap_or(0);
+            // Inode is at index 9
+            if let Ok(inode) = parts[9].parse::<i32>() {
+                results.push(NetEntry {
+                    local_addr,
+                    local_port,
+                    remote_addr,
+                    state: parts[3].to_string(),
+                    inode,
+                    rx_queue,
+                });
             }
         }
     }
     results
 }
 
-// Map inode to PID by scanning /proc/[pid]/fd
-fn get_pids_for_inodes(inodes: &HashSet<i32>) -> HashMap<i32, i32> {
-    let mut map = HashMap::new();
-    if let Ok(entries) = fs::read_dir("/proc") {
+// Keeps only sockets in `state`
+fn scan_proc_net(file: &str, state: &str) -> Vec<NetEntry> {
+    parse_proc_net(file)
+        .into_iter()
+        .filter(|e| e.state == state)
+        .collect()
+}
+
+// Helper to read /proc/net/tcp and tcp6
+fn scan_proc_net_tcp(file: &str) -> Vec<NetEntry> {
+    // State 0A means LISTEN
+    scan_proc_net(file, "0A")
+}
+
+// Helper to read /proc/net/udp and udp6
+fn scan_proc_net_udp(file: &str) -> Vec<NetEntry> {
+    // UDP has no LISTEN state; a bound but unconnected socket stays in 07 (CLOSE)
+    scan_proc_net(file, "07")
+        .into_iter()
+        .filter(|e| e.local_port != 0)
+        .collect()
+}
+
+// Map inode to every PID holding it by scanning /proc/[pid]/fd
+pub(crate) fn get_pids_for_inodes(inodes: &HashSet<i32>) -> HashMap<i32, Vec<i32>> {
+    let mut map: HashMap<i32, Vec<i32>> = HashMap::new();
+    if let Ok(entries) = fs::read_dir(proc_root::proc_root()) {
         for entry in entries.flatten() {
             if let Ok(file_name) = entry.file_name().into_string() {
                 if let Ok(pid) = file_name.parse::<i32>() {
-                    let fd_path = format!("/proc/{}/fd", pid);
+                    let fd_path = proc_root::proc_path(&format!("{}/fd", pid));
                     if let Ok(fd_entries) = fs::read_dir(fd_path) {
                         for fd in fd_entries.flatten() {
                             if let Ok(target) = fs::read_link(fd.path()) {
@@ -64,7 +304,11 @@ fn get_pids_for_inodes(inodes: &HashSet<i32>) -> HashMap<i32, i32> {
                                         let inode_str = &target_str[8..target_str.len()-1];
                                         if let Ok(inode) = inode_str.parse::<i32>() {
                                             if inodes.contains(&inode) {
-                                                map.insert(inode, pid);
+                                                let pids = map.entry(inode).or_default();
+                                                // One process may hold the socket on several fds
+                                                if !pids.contains(&pid) {
+                                                    pids.push(pid);
+                                                }
                                             }
                                         }
                                     }
@@ -76,224 +320,376 @@ fn get_pids_for_inodes(inodes: &HashSet<i32>) -> HashMap<i32, i32> {
             }
         }
     }
+    for pids in map.values_mut() {
+        pids.sort_unstable();
+    }
     map
 }
 
+// True if one of the process' ancestors is in `pids`
+fn has_ancestor_in(pid: i32, pids: &[i32], processes: &HashMap<i32, proc_root::ProcEntry>) -> bool {
+    let mut current = pid;
+    // Bounded walk, a broken capture must not loop forever
+    for _ in 0..64 {
+        let Some(parent) = processes.get(&current).map(|p| p.ppid) else { return false; };
+        if parent <= 1 {
+            return false;
+        }
+        if pids.contains(&parent) {
+            return true;
+        }
+        current = parent;
+    }
+    false
+}
+
+// Order the holders of one socket: the top-most process first, then the rest by PID
+fn socket_owners(pids: &[i32], processes: &HashMap<i32, proc_root::ProcEntry>) -> Vec<SocketOwner> {
+    let children: Vec<i32> = pids.iter().copied().filter(|&p| has_ancestor_in(p, pids, processes)).collect();
+    let Some(main) = pids.iter().copied().find(|p| !children.contains(p)).or(pids.first().copied()) else {
+        return Vec::new();
+    };
+    let main_pgid = processes.get(&main).map(|p| p.pgid);
+
+    let mut owners = vec![SocketOwner {
+        pid: main,
+        process_name: processes.get(&main).map(|p| p.name.clone()),
+        role: if children.is_empty() { "owner" } else { "parent" }.to_string(),
+        start_ticks: processes.get(&main).map(|p| p.start_ticks),
+    }];
+    for &pid in pids.iter().filter(|&&p| p != main) {
+        let process = processes.get(&pid);
+        let role = if children.contains(&pid) {
+            "child"
+        } else if main_pgid.is_some() && process.map(|p| p.pgid) == main_pgid {
+            "group"
+        } else {
+            "other"
+        };
+        owners.push(SocketOwner {
+            pid,
+            process_name: process.map(|p| p.name.clone()),
+            role: role.to_string(),
+            start_ticks: process.map(|p| p.start_ticks),
+        });
+    }
+    owners
+}
+
 // --- Implementation Functions (Not Tauri Commands) ---
 
-fn get_ports_impl() -> Vec<PortInfo> {
-    let mut system = System::new_all();
-    system.refresh_processes(ProcessesToUpdate::All, true);
-    let users = Users::new_with_refreshed_list();
+pub(crate) fn get_ports_impl() -> Vec<PortInfo> {
+    scan_ports(false)
+}
 
-    // Sistemski portovi koji se ignorišu (DNS, Samba, CUPS, itd.)
-    let system_ports: HashSet<u16> = [53, 139, 445, 631, 22, 80, 443].iter().cloned().collect();
-    
-    // Sistemski procesi koji se ignorišu
-    let system_processes: HashSet<&str> = [
-        "systemd", "dbus", "NetworkManager", "pipewire", "pulseaudio",
-        "cupsd", "smbd", "nmbd", "avahi-daemon", "systemd-resolved",
-        "systemd-logind", "systemd-udevd", "systemd-timesyncd",
-        "libvirtd", "dnsmasq", "sshd"
-    ].iter().cloned().collect();
+// What the list, GUI and Waybar show: listeners are also probed for their protocol,
+// kill plans and the watcher never connect to anything
+fn get_ports_probed() -> Vec<PortInfo> {
+    scan_ports(true)
+}
+
+fn scan_ports(probe: bool) -> Vec<PortInfo> {
+    // Read straight from procfs so a captured tree (--proc-root) replays the same way
+    let tree = process_tree::ProcessTree::load();
+    let processes = tree.processes();
+    let users = proc_root::user_names();
+    // Ignore/include rules from config.toml (system ports, daemons and users by default)
+    let config = config::load();
+    let rules = config::RuleSet::new(&config);
 
     let mut ports_map: HashMap<String, PortInfo> = HashMap::new();
     
-    // 1. Get all listening ports and their inodes
-    let mut tcp4 = scan_proc_net_tcp("/proc/net/tcp");
-    let tcp6 = scan_proc_net_tcp("/proc/net/tcp6");
-    tcp4.extend(tcp6);
+    // 1. Get all listening ports and their inodes from the selected socket source
+    let sockets = sources::scan_listening();
     
-    let inodes: HashSet<i32> = tcp4.iter().map(|(_, inode)| *inode).collect();
+    let inodes: HashSet<i32> = sockets.iter().filter(|r| r.pids.is_empty()).map(|r| r.entry.inode).collect();
     
     // 2. Find PIDs for these inodes
     let inode_pid_map = get_pids_for_inodes(&inodes);
+
+    let mut containers = containers::ContainerResolver::new();
+    let mut projects = project::ProjectResolver::new();
+    let mut units = systemd::UnitResolver::new();
+    let mut socket_units = systemd::SocketUnitResolver::new();
     
-    for (port, inode) in tcp4 {
-        // Ignoriši sistemske portove
-        if system_ports.contains(&port) {
-            continue;
-        }
-        
+    for record in sockets {
+        let protocol = record.protocol;
+        let entry = record.entry;
+        let port = entry.local_port;
+        let inode = entry.inode;
         let port_str = port.to_string();
-        let pid = inode_pid_map.get(&inode).copied();
-        
-        let mut process_name = None;
-        let mut user = "unknown".to_string();
-        let mut is_system = false;
-
-        if let Some(p) = pid {
-            let pid_u32 = p as usize;
-            if let Some(process) = system.process(Pid::from(pid_u32)) {
-                // Fix: Process::name returns &OsStr, needs to_string_lossy
-                let proc_name = process.name().to_string_lossy().into_owned();
-                
-                // Ignoriši sistemske procese
-                if system_processes.contains(proc_name.as_str()) {
-                    continue;
-                }
-                
-                process_name = Some(proc_name);
-                
-                if let Some(uid) = process.user_id() {
-                    if let Some(u) = users.get_user_by_id(uid) {
-                        // Fix: User::name returns &str, needs to_string
-                        user = u.name().to_string();
-                        // Ignoriši root i sistemske korisnike
-                        if user == "root" || user == "system" || user == "daemon" || user == "nobody" {
-                            is_system = true;
-                        }
-                    }
-                }
-            } else {
-                is_system = true;
-            }
+        let pids = if record.pids.is_empty() {
+            inode_pid_map.get(&inode).cloned().unwrap_or_default()
         } else {
-            // Portovi bez PID-a ili portovi < 1024 su verovatno sistemski
-            if port < 1024 {
-                continue;
-            }
+            record.pids
+        };
+        let owners = socket_owners(&pids, processes);
+        let pid = owners.first().map(|o| o.pid);
+        let owner = pid.and_then(|p| processes.get(&p));
+        let process_name = owner.map(|p| p.name.clone());
+        let user = owner
+            .and_then(|p| p.uid)
+            .and_then(|uid| users.get(&uid).cloned())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        // Ports published by a container are shown even when root's docker-proxy holds them
+        let owner_name = owner.map(|p| p.name.as_str());
+        let container = containers.resolve(pid, owner_name, protocol, port);
+
+        // Held by systemd itself or by nobody we can see: maybe a .socket unit listens here
+        let socket_unit = if container.is_none() && (pid.is_none() || owner_name == Some("systemd")) {
+            socket_units.resolve(protocol, entry.local_addr, port)
+        } else {
+            None
+        };
+        // Containers and socket units are shown even though root processes hold their ports
+        let managed = container.is_some() || socket_unit.is_some();
+
+        let cmdline = match pid {
+            Some(p) if rules.uses_cmdlines() && !managed => cmdline::cmdline_of(p),
+            _ => None,
+        };
+        // The owner of a managed port is docker-proxy or systemd, only the port itself counts
+        let subject = if managed {
+            config::Subject { port: Some(port), ..Default::default() }
+        } else {
+            config::Subject { port: Some(port), process: process_name.as_deref(), user: Some(&user), cmdline: cmdline.as_deref() }
+        };
+        if rules.hides(&subject) {
+            continue;
+        }
+        // Owners we cannot see (other users' processes), and ports nobody holds below 1024,
+        // belong to system services unless a rule asks for them
+        if !managed && !rules.includes(&subject) && (if pid.is_some() { owner.is_none() } else { port < 1024 }) {
+            continue;
         }
 
-        // Preskoči sistemske portove i procese
-        if is_system || user == "root" || user == "system" || user == "root/system" {
+        // Same port on different addresses (127.0.0.1 vs 0.0.0.0 vs ::) gets its own row
+        let bind_address = entry.local_addr.to_string();
+        let key = format!("{}/{}/{}/{}", record.netns, protocol, bind_address, port_str);
+
+        // SO_REUSEPORT groups show up as several sockets on one address, merge their holders
+        if let Some(existing) = ports_map.get_mut(&key) {
+            for owner in owners {
+                if !existing.owners.iter().any(|o| o.pid == owner.pid) {
+                    existing.owners.push(owner);
+                }
+            }
             continue;
         }
 
-        ports_map.insert(port_str.clone(), PortInfo {
+        let details = pid.zip(process_name.as_deref()).map(|(p, name)| cmdline::process_details(p, name)).unwrap_or_default();
+        // A container's cwd is a path in its own filesystem, and a capture has no files to look at
+        let project = match &details.cwd {
+            Some(cwd) if container.is_none() && proc_root::is_live() => projects.resolve(cwd),
+            _ => None,
+        };
+        let unit = match &socket_unit {
+            // The service the socket starts, not systemd itself
+            Some(socket) => socket.service.clone().map(|service| (systemd::UnitRef { name: service, user: socket.user }, None)),
+            None if container.is_none() => pid.and_then(|p| units.resolve(p)),
+            None => None,
+        };
+        ports_map.insert(key, PortInfo {
             port: port_str,
+            protocol: protocol.to_string(),
+            bind_address,
+            family: if entry.local_addr.is_ipv4() { "ipv4" } else { "ipv6" }.to_string(),
+            netns: record.netns,
+            namespaced: record.namespaced,
             pid,
             process_name,
+            display_name: if details.display_name.is_empty() { None } else { Some(details.display_name) },
+            cmdline: details.cmdline,
+            cwd: details.cwd,
+            exe: details.exe,
+            project: project.as_ref().map(|p| p.name.clone()),
+            project_root: project.as_ref().map(|p| p.root.clone()),
+            git_branch: project.and_then(|p| p.branch),
+            owners,
+            systemd_unit: unit.as_ref().map(|(u, _)| u.name.clone()),
+            systemd_scope: socket_unit.as_ref().map(|s| s.scope())
+                .or(unit.as_ref().map(|(u, _)| u.scope()))
+                .map(|s| s.to_string()),
+            restart_policy: unit.and_then(|(_, policy)| policy),
+            socket_unit: socket_unit.as_ref().map(|s| s.name.clone()),
+            started_at: pid.and_then(|p| processes.get(&p)).and_then(|p| proc_root::started_at(p.start_ticks)),
+            start_ticks: pid.and_then(|p| processes.get(&p)).map(|p| p.start_ticks),
+            ancestors: pid.map(|p| tree.ancestors(p)).unwrap_or_default(),
             user,
+            category: String::new(),
+            service_name: None,
+            protocol_guess: None,
+            protocol_detail: None,
+            health: None,
+            socket_options: record.options,
+            container_id: container.as_ref().map(|c| c.id.clone()),
+            container_name: container.as_ref().map(|c| c.name.clone()),
+            compose_project: container.and_then(|c| c.compose_project),
         });
     }
 
     let mut ports: Vec<PortInfo> = ports_map.into_values().collect();
-    ports.sort_by_key(|p| p.port.parse::<u32>().unwrap_or(0));
+    ports.sort_by_key(|p| (p.port.parse::<u32>().unwrap_or(0), p.protocol.clone(), p.bind_address.clone(), p.namespaced));
+    if probe {
+        probe::annotate(&mut ports);
+    }
+    classifier::classify(&mut ports, &config.categories);
     ports
 }
 
-fn kill_port_impl(pid: i32) -> bool {
-    let s = System::new(); 
-    if let Some(process) = s.process(Pid::from(pid as usize)) {
-        return process.kill();
-    }
-    
-    // Fallback
-    let _ = Command::new("kill").arg(pid.to_string()).status();
-    let status = Command::new("kill").arg("-9").arg(pid.to_string()).status();
-    match status {
-         Ok(s) => s.success(),
-         Err(_) => false,
+fn get_ports_filtered(filter: &PortFilter) -> Vec<PortInfo> {
+    get_ports_probed()
+        .into_iter()
+        .filter(|p| filter.matches(p))
+        .collect()
+}
+
+// The policy from config.toml unless the caller brings its own
+fn policy_or_default(policy: Option<TerminationPolicy>) -> TerminationPolicy {
+    policy.unwrap_or_else(|| config::load().kill)
+}
+
+// start_ticks as listed by get_ports/get_processes; None kills whatever has the PID now
+fn bulk_kill_impl(request: &BulkKill, policy: &TerminationPolicy) -> Result<KillReport, String> {
+    plan::execute(&plan::plan(request, policy)?, policy)
+}
+
+fn kill_pid_impl(pid: i32, start_ticks: Option<u64>, policy: &TerminationPolicy) -> Result<KillReport, String> {
+    terminate::terminate_targets(&[terminate::Target { pid, start_ticks }], policy)
+}
+
+// Every process holding a socket on the port, unfiltered, so nothing is left behind.
+// Only in one network namespace (the host's for None): a container's 8080 is not the host's
+pub(crate) fn port_holders(protocol: Option<&str>, port: u16, netns: Option<&str>) -> Vec<SocketOwner> {
+    let records: Vec<_> = sources::scan_listening()
+        .into_iter()
+        .filter(|r| r.entry.local_port == port && protocol.map(|p| p.eq_ignore_ascii_case(r.protocol)).unwrap_or(true))
+        .filter(|r| netns.map(|id| r.netns == id).unwrap_or(!r.namespaced))
+        .collect();
+    let inodes: HashSet<i32> = records.iter().map(|r| r.entry.inode).collect();
+    let inode_pid_map = get_pids_for_inodes(&inodes);
+
+    let mut pids: Vec<i32> = Vec::new();
+    for record in records {
+        pids.extend(record.pids);
+        pids.extend(inode_pid_map.get(&record.entry.inode).into_iter().flatten());
     }
+    pids.sort_unstable();
+    pids.dedup();
+    socket_owners(&pids, &proc_root::process_table())
 }
 
 // --- Tauri Commands ---
 
+// async runs it off the main thread; health comes from the watcher's last round of checks
 #[tauri::command]
-fn get_ports() -> Vec<PortInfo> {
-    get_ports_impl()
+async fn get_ports(filter: Option<PortFilter>) -> Vec<PortInfo> {
+    let mut ports = get_ports_filtered(&filter.unwrap_or_default());
+    health::annotate_cached(&mut ports);
+    ports
+}
+
+#[tauri::command]
+fn get_connections() -> Vec<ConnectionSummary> {
+    connections::get_connections_impl()
+}
+
+#[tauri::command]
+fn get_unix_sockets() -> Vec<UnixSocketInfo> {
+    unix_sockets::get_unix_sockets_impl()
 }
 
 #[tauri::command]
 fn get_processes() -> Vec<ProcessInfo> {
-    let mut system = System::new_all();
-    system.refresh_processes(ProcessesToUpdate::All, true);
-    let users = Users::new_with_refreshed_list();
-    
-    // Sistemski procesi koji se ignorišu
-    let system_processes: HashSet<&str> = [
-        "systemd", "dbus", "NetworkManager", "pipewire", "pulseaudio",
-        "cupsd", "smbd", "nmbd", "avahi-daemon", "systemd-resolved",
-        "systemd-logind", "systemd-udevd", "systemd-timesyncd",
-        "libvirtd", "dnsmasq", "sshd", "kernel", "kthreadd",
-        "ksoftirqd", "migration", "rcu_", "watchdog", "kworker"
-    ].iter().cloned().collect();
-    
-    let mut processes = Vec::new();
-    
-    for (pid, process) in system.processes() {
-        let proc_name = process.name().to_string_lossy().into_owned();
-        
-        // Ignoriši sistemske procese
-        if system_processes.iter().any(|&sys_proc| proc_name.starts_with(sys_proc)) {
-            continue;
-        }
-        
-        let mut user = "unknown".to_string();
-        let mut is_system = false;
-        
-        if let Some(uid) = process.user_id() {
-             if let Some(u) = users.get_user_by_id(uid) {
-                 user = u.name().to_string();
-                 // Ignoriši root i sistemske korisnike
-                 if user == "root" || user == "system" || user == "daemon" || user == "nobody" {
-                     is_system = true;
-                 }
-             } else {
-                 // Ako ne može da nađe korisnika, verovatno je sistemski
-                 is_system = true;
-             }
-        }
+    get_processes_list()
+}
 
-        // Preskoči sistemske procese
-        if is_system || user == "root" || user == "system" || user == "daemon" {
-            continue;
-        }
+// Every kill command takes an optional policy, config.toml's [kill] section otherwise
 
-        processes.push(ProcessInfo {
-            pid: pid.as_u32() as i32,
-            name: proc_name,
-            cpu: format!("{:.1}", process.cpu_usage()),
-            mem: format!("{:.1}", (process.memory() as f64 / 1024.0 / 1024.0)), // MB
-            user,
-        });
-    }
-    
-    processes.sort_by(|a, b| {
-        let cpu_a = a.cpu.parse::<f64>().unwrap_or(0.0);
-        let cpu_b = b.cpu.parse::<f64>().unwrap_or(0.0);
-        cpu_b.partial_cmp(&cpu_a).unwrap_or(std::cmp::Ordering::Equal)
-    });
+#[tauri::command]
+fn kill_port(pid: i32, start_ticks: Option<u64>, policy: Option<TerminationPolicy>) -> Result<KillReport, String> {
+    kill_pid_impl(pid, start_ticks, &policy_or_default(policy))
+}
 
-    processes.truncate(100);
-    processes
+#[tauri::command]
+fn kill_port_holders(protocol: Option<String>, port: u16, netns: Option<String>, policy: Option<TerminationPolicy>) -> Result<KillReport, String> {
+    bulk_kill_impl(&BulkKill::Port { port, protocol, netns }, &policy_or_default(policy))
 }
 
 #[tauri::command]
-fn kill_port(pid: i32) -> bool {
-    kill_port_impl(pid)
+fn kill_process_tree(pid: i32, mode: KillMode, start_ticks: Option<u64>, policy: Option<TerminationPolicy>) -> Result<KillReport, String> {
+    bulk_kill_impl(&BulkKill::Tree { pid, mode, start_ticks }, &policy_or_default(policy))
 }
 
 #[tauri::command]
-fn kill_process(pid: i32) -> bool {
-    kill_port_impl(pid)
+fn kill_project(project: String, policy: Option<TerminationPolicy>) -> Result<KillReport, String> {
+    bulk_kill_impl(&BulkKill::Project { project }, &policy_or_default(policy))
 }
 
 #[tauri::command]
-fn kill_processes_by_name(process_name: String) -> Result<i32, String> {
-    let mut system = System::new_all();
-    system.refresh_processes(ProcessesToUpdate::All, true);
-    
-    let mut killed_count = 0;
-    let process_name_lower = process_name.to_lowercase();
-    
-    for (pid, process) in system.processes() {
-        let proc_name = process.name().to_string_lossy().to_lowercase();
-        
-        // Proveri da li se ime procesa poklapa (tačno ili sadrži)
-        if proc_name == process_name_lower || proc_name.contains(&process_name_lower) {
-            if kill_port_impl(pid.as_u32() as i32) {
-                killed_count += 1;
-            }
-        }
-    }
-    
-    if killed_count > 0 {
-        Ok(killed_count)
-    } else {
-        Err(format!("No processes found matching '{}'", process_name))
+fn kill_process(pid: i32, start_ticks: Option<u64>, policy: Option<TerminationPolicy>) -> Result<KillReport, String> {
+    kill_pid_impl(pid, start_ticks, &policy_or_default(policy))
+}
+
+#[tauri::command]
+fn kill_processes_by_name(process_name: String, mode: Option<MatchMode>, policy: Option<TerminationPolicy>) -> Result<KillReport, String> {
+    bulk_kill_impl(&BulkKill::Name { name: process_name, mode: mode.unwrap_or_default() }, &policy_or_default(policy))
+}
+
+// Bulk kills in two steps: the GUI confirms the preview, then kills exactly that plan
+#[tauri::command]
+fn preview_bulk_kill(request: BulkKill, policy: Option<TerminationPolicy>) -> Result<KillPlan, String> {
+    plan::plan(&request, &policy_or_default(policy))
+}
+
+#[tauri::command]
+fn kill_planned(plan: KillPlan, policy: Option<TerminationPolicy>) -> Result<KillReport, String> {
+    plan::execute(&plan, &policy_or_default(policy))
+}
+
+#[tauri::command]
+fn stop_container(container_id: String) -> Result<String, String> {
+    containers::stop_container_impl(&container_id)
+}
+
+#[tauri::command]
+fn stop_unit(unit: String, user: bool) -> Result<String, String> {
+    systemd::stop_unit_impl(&unit, user)
+}
+
+#[tauri::command]
+fn disable_unit(unit: String, user: bool) -> Result<String, String> {
+    systemd::disable_unit_impl(&unit, user)
+}
+
+#[tauri::command]
+fn get_config() -> Config {
+    config::load()
+}
+
+// Validates and writes config.toml, the next scan picks it up
+#[tauri::command]
+fn update_config(config: Config) -> Result<Config, String> {
+    config::save(&config)?;
+    Ok(config)
+}
+
+// Only web URLs, xdg-open would happily run a .desktop file or open any path
+fn open_url_impl(url: &str) -> Result<(), String> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(format!("Not a web URL: {}", url));
     }
+    Command::new("xdg-open")
+        .arg(url)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to execute xdg-open: {}", e))
+}
+
+#[tauri::command]
+fn open_in_browser(url: String) -> Result<(), String> {
+    open_url_impl(&url)
 }
 
 #[tauri::command]
@@ -437,6 +833,14 @@ fn setup_waybar() -> Result<String, String> {
     background: rgba(16, 185, 129, 0.15);
     color: #10b981;
 }
+#custom-ppkiller.slow {
+    background: rgba(245, 158, 11, 0.15);
+    color: #f59e0b;
+}
+#custom-ppkiller.hung {
+    background: rgba(239, 68, 68, 0.2);
+    color: #ef4444;
+}
 "#;
         css.push_str(css_append);
         fs::write(&css_path, css).map_err(|_| "Failed to write style.css")?;
@@ -457,57 +861,78 @@ pub fn get_ports_list() -> Vec<PortInfo> {
     get_ports_impl()
 }
 
+pub fn get_ports_list_filtered(filter: &PortFilter) -> Vec<PortInfo> {
+    get_ports_filtered(filter)
+}
+
+// With protocol guesses, connects to every new TCP listener
+pub fn get_ports_list_probed() -> Vec<PortInfo> {
+    get_ports_probed()
+}
+
+// Connects to every TCP listener, takes up to a second when one is hung
+pub fn check_ports_health(ports: &mut [PortInfo]) {
+    health::check(ports)
+}
+
+// Accept queue against backlog from the scan alone, no connections
+pub fn check_ports_queue(ports: &mut [PortInfo]) {
+    health::check_queues(ports)
+}
+
+pub fn get_connections_list() -> Vec<ConnectionSummary> {
+    connections::get_connections_impl()
+}
+
+pub fn get_unix_sockets_list() -> Vec<UnixSocketInfo> {
+    unix_sockets::get_unix_sockets_impl()
+}
+
 pub fn get_processes_list() -> Vec<ProcessInfo> {
-    let mut system = System::new_all();
-    system.refresh_processes(ProcessesToUpdate::All, true);
-    let users = Users::new_with_refreshed_list();
-    
-    // Sistemski procesi koji se ignorišu
-    let system_processes: HashSet<&str> = [
-        "systemd", "dbus", "NetworkManager", "pipewire", "pulseaudio",
-        "cupsd", "smbd", "nmbd", "avahi-daemon", "systemd-resolved",
-        "systemd-logind", "systemd-udevd", "systemd-timesyncd",
-        "libvirtd", "dnsmasq", "sshd", "kernel", "kthreadd",
-        "ksoftirqd", "migration", "rcu_", "watchdog", "kworker"
-    ].iter().cloned().collect();
+    let users = proc_root::user_names();
+
+    // (pid, name, uid, cpu %, memory in bytes)
+    let snapshot: Vec<(i32, String, Option<u32>, f32, u64)> = if proc_root::is_live() {
+        let mut system = System::new_all();
+        system.refresh_processes(ProcessesToUpdate::All, true);
+        system.processes().iter()
+            .map(|(pid, p)| (pid.as_u32() as i32, p.name().to_string_lossy().into_owned(), p.user_id().map(|u| **u), p.cpu_usage(), p.memory()))
+            .collect()
+    } else {
+        // A capture has no CPU samples, only the resident set size
+        proc_root::process_table().into_iter()
+            .map(|(pid, p)| (pid, p.name, p.uid, 0.0, p.rss_kb * 1024))
+            .collect()
+    };
     
+    let rules = config::RuleSet::new(&config::load());
     let mut processes = Vec::new();
     
-    for (pid, process) in system.processes() {
-        let proc_name = process.name().to_string_lossy().into_owned();
-        
-        // Ignoriši sistemske procese
-        if system_processes.iter().any(|&sys_proc| proc_name.starts_with(sys_proc)) {
+    for (pid, proc_name, uid, cpu, memory) in snapshot {
+        let known_user = uid.and_then(|uid| users.get(&uid).cloned());
+        let user = known_user.clone().unwrap_or_else(|| "unknown".to_string());
+        let cmdline = if rules.uses_cmdlines() { cmdline::cmdline_of(pid) } else { None };
+        let subject = config::Subject { port: None, process: Some(&proc_name), user: Some(&user), cmdline: cmdline.as_deref() };
+        if rules.hides(&subject) {
             continue;
         }
-        
-        let mut user = "unknown".to_string();
-        let mut is_system = false;
-        
-        if let Some(uid) = process.user_id() {
-             if let Some(u) = users.get_user_by_id(uid) {
-                 user = u.name().to_string();
-                 // Ignoriši root i sistemske korisnike
-                 if user == "root" || user == "system" || user == "daemon" || user == "nobody" {
-                     is_system = true;
-                 }
-             } else {
-                 // Ako ne može da nađe korisnika, verovatno je sistemski
-                 is_system = true;
-             }
-        }
-
-        // Preskoči sistemske procese
-        if is_system || user == "root" || user == "system" || user == "daemon" {
+        // Ako ne može da nađe korisnika, verovatno je sistemski
+        if uid.is_some() && known_user.is_none() && !rules.includes(&subject) {
             continue;
         }
 
+        let details = cmdline::process_details(pid, &proc_name);
         processes.push(ProcessInfo {
-            pid: pid.as_u32() as i32,
+            pid,
             name: proc_name,
-            cpu: format!("{:.1}", process.cpu_usage()),
-            mem: format!("{:.1}", (process.memory() as f64 / 1024.0 / 1024.0)), // MB
+            display_name: details.display_name,
+            cmdline: details.cmdline,
+            cwd: details.cwd,
+            exe: details.exe,
+            cpu: format!("{:.1}", cpu),
+            mem: format!("{:.1}", (memory as f64 / 1024.0 / 1024.0)), // MB
             user,
+            start_ticks: proc_root::read_process(pid).map(|p| p.start_ticks),
         });
     }
     
@@ -521,26 +946,109 @@ pub fn get_processes_list() -> Vec<ProcessInfo> {
     processes
 }
 
-pub fn kill_all_ports() {
-    let ports = get_ports_impl();
-    for p in ports {
-        if let Some(pid) = p.pid {
-            let _ = kill_port_impl(pid);
-        }
+// Read procfs from a `ppkiller capture` directory instead of the live /proc
+pub fn set_proc_root(path: &str) -> Result<(), String> {
+    let root = PathBuf::from(path);
+    if !root.join("net").is_dir() {
+        return Err(format!("{} does not look like a ppkiller capture (no net/ directory)", path));
+    }
+    proc_root::set_proc_root(root);
+    Ok(())
+}
+
+pub fn capture_proc(output: &str) -> Result<PathBuf, String> {
+    capture::capture_impl(std::path::Path::new(output))
+}
+
+pub fn set_socket_source(source: &str) -> Result<(), String> {
+    sources::set_source(source.parse()?);
+    Ok(())
+}
+
+pub fn stop_container_by_id(container_id: &str) -> Result<String, String> {
+    containers::stop_container_impl(container_id)
+}
+
+pub fn termination_policy() -> TerminationPolicy {
+    config::load().kill
+}
+
+pub fn preview_kill(request: &BulkKill, policy: &TerminationPolicy) -> Result<KillPlan, String> {
+    plan::plan(request, policy)
+}
+
+pub fn execute_kill_plan(plan: &KillPlan, policy: &TerminationPolicy) -> Result<KillReport, String> {
+    plan::execute(plan, policy)
+}
+
+// (unit, user) behind a port; the holders are looked at directly, the default rules hide
+// root's services such as nginx.service
+pub fn systemd_unit_for_port(port: u16) -> Option<(String, bool)> {
+    // Only stopping the .socket frees a socket-activated port
+    let listed = get_ports_impl()
+        .into_iter()
+        .find(|p| p.port == port.to_string() && (p.socket_unit.is_some() || p.systemd_unit.is_some()));
+    if let Some(p) = listed {
+        let user = p.systemd_scope.as_deref() == Some("user");
+        return p.socket_unit.or(p.systemd_unit).map(|unit| (unit, user));
     }
+    let mut units = systemd::UnitResolver::new();
+    port_holders(None, port, None).iter().find_map(|o| units.resolve(o.pid)).map(|(unit, _)| (unit.name, unit.user))
+}
+
+pub fn stop_systemd_unit(unit: &str, user: bool) -> Result<String, String> {
+    systemd::stop_unit_impl(unit, user)
+}
+
+pub fn disable_systemd_unit(unit: &str, user: bool) -> Result<String, String> {
+    systemd::disable_unit_impl(unit, user)
+}
+
+pub fn config_get(key: Option<&str>) -> Result<String, String> {
+    config::get_impl(key)
+}
+
+pub fn config_set(key: &str, value: &str) -> Result<PathBuf, String> {
+    config::set_impl(key, value)
+}
+
+pub fn config_edit() -> Result<PathBuf, String> {
+    config::edit_impl()
+}
+
+pub fn open_url(url: &str) -> Result<(), String> {
+    open_url_impl(url)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_log::Builder::new().build())
-        .invoke_handler(tauri::generate_handler![get_ports, get_processes, kill_port, kill_process, kill_processes_by_name, open_terminal, setup_waybar])
-        .setup(|_app| {
+        .invoke_handler(tauri::generate_handler![get_ports, get_connections, get_unix_sockets, get_processes, kill_port, kill_port_holders, kill_process, kill_process_tree, kill_project, kill_processes_by_name, preview_bulk_kill, kill_planned, stop_container, stop_unit, disable_unit, get_config, update_config, open_in_browser, open_terminal, setup_waybar])
+        .setup(|app| {
+            // Push port changes and health to the UI instead of letting it poll
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                use tauri::Emitter;
+                let mut watcher = PortWatcher::new();
+                for round in 0u64.. {
+                    // Every 10 seconds, connecting to every listener on each poll is too much
+                    if round % 5 == 0 && health::refresh(&mut get_ports_probed()) {
+                        let _ = handle.emit("ports-health", ());
+                    }
+                    std::thread::sleep(std::time::Duration::from_secs(2));
+                    let events = watcher.poll();
+                    if !events.is_empty() {
+                        let _ = handle.emit("ports-changed", &events);
+                    }
+                }
+            });
+
             // Open devtools in development mode
             #[cfg(debug_assertions)]
             {
                 use tauri::Manager;
-                if let Some(window) = _app.get_webview_window("main") {
+                if let Some(window) = app.get_webview_window("main") {
                     let _ = window.open_devtools();
                     println!("DevTools opened automatically (debug mode)");
                 } else {
@@ -552,3 +1060,46 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // /proc/net/tcp* print every 32-bit word in host byte order
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn hex_addresses_of_both_families() {
+        assert_eq!(parse_hex_addr("0100007F"), Some("127.0.0.1".parse().unwrap()));
+        assert_eq!(parse_hex_addr("00000000"), Some("0.0.0.0".parse().unwrap()));
+        assert_eq!(parse_hex_addr("00000000000000000000000001000000"), Some("::1".parse().unwrap()));
+        assert_eq!(parse_hex_addr("0000000000000000FFFF00000100007F"), Some("::ffff:127.0.0.1".parse().unwrap()));
+        assert_eq!(parse_hex_socket("0100007F:1F90"), Some(("127.0.0.1".parse().unwrap(), 8080)));
+    }
+
+    #[test]
+    fn malformed_hex_addresses() {
+        assert_eq!(parse_hex_addr(""), None);
+        assert_eq!(parse_hex_addr("0100007G"), None);
+        // Two words are neither family
+        assert_eq!(parse_hex_addr("0100007F0100007F"), None);
+    }
+
+    #[test]
+    fn port_filter_rejects_unknown_values() {
+        assert!(PortFilter { netns: Some("host".into()), protocol: Some("TCP".into()), ..Default::default() }.validate().is_ok());
+        assert!(PortFilter { netns: Some("bogus".into()), ..Default::default() }.validate().is_err());
+        assert!(PortFilter { protocol: Some("sctp".into()), ..Default::default() }.validate().is_err());
+        assert!(PortFilter { category: Some("Database".into()), ..Default::default() }.validate().is_ok());
+        assert!(PortFilter { category: Some("no-such-category".into()), ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn port_filter_splits_host_and_namespaced() {
+        let host = PortInfo { protocol: "tcp".into(), ..Default::default() };
+        let container = PortInfo { protocol: "tcp".into(), namespaced: true, ..Default::default() };
+        let only_host = PortFilter { netns: Some("host".into()), ..Default::default() };
+        let only_namespaced = PortFilter { netns: Some("namespaced".into()), ..Default::default() };
+        assert!(only_host.matches(&host) && !only_host.matches(&container));
+        assert!(only_namespaced.matches(&container) && !only_namespaced.matches(&host));
+    }
+}
@@ -1,9 +1,8 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::process::{Command, Stdio};
-use regex::Regex;
 
 #[derive(Parser)]
 #[command(name = "ppkiller")]
@@ -11,21 +10,334 @@ use regex::Regex;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Socket source backend: procfs, netlink or ss (default: $PPKILLER_SOURCE or procfs). netlink and ss see only the current network namespace, the others are always read from procfs
+    #[arg(long, global = true)]
+    source: Option<String>,
+    /// Read procfs from a `ppkiller capture` directory instead of /proc
+    #[arg(long, global = true, value_name = "DIR")]
+    proc_root: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Show the Rofi menu (Slika 1)
-    Menu,
+    Menu {
+        /// Only show ports of this protocol (tcp or udp)
+        #[arg(short, long)]
+        protocol: Option<String>,
+        /// Only show ports of this category (web, database, messaging, devtools, system, other)
+        #[arg(short, long)]
+        category: Option<String>,
+        /// Only show "host" or "namespaced" (containers, sandboxes) listeners
+        #[arg(long)]
+        netns: Option<String>,
+        /// Show Unix domain sockets instead of ports
+        #[arg(short, long)]
+        unix: bool,
+    },
     /// Output for Waybar module
     Waybar,
     /// Output all ports in JSON
     List {
         #[arg(short, long)]
         json: bool,
+        /// Only show ports of this protocol (tcp or udp)
+        #[arg(short, long)]
+        protocol: Option<String>,
+        /// Only show "host" or "namespaced" (containers, sandboxes) listeners
+        #[arg(long)]
+        netns: Option<String>,
+        /// Only show ports of this category (web, database, messaging, devtools, system, other)
+        #[arg(short, long)]
+        category: Option<String>,
+        /// List Unix domain sockets instead of ports
+        #[arg(short, long)]
+        unix: bool,
+        /// Group ports, currently only by "project"
+        #[arg(long, value_name = "KEY")]
+        group_by: Option<String>,
     },
-    /// Kill all ports
-    KillAll,
+    /// Show established/TIME_WAIT/CLOSE_WAIT connections per listening port
+    Connections {
+        #[arg(short, long)]
+        json: bool,
+        /// Number of scans to take, CLOSE_WAIT growth needs at least two
+        #[arg(short, long, default_value_t = 2)]
+        samples: u32,
+        /// Seconds between scans
+        #[arg(short, long, default_value_t = 1)]
+        interval: u64,
+    },
+    /// Live port table, redrawn in place; new rows green, closed rows red
+    Watch {
+        /// Seconds between refreshes
+        #[arg(short, long, default_value_t = 2)]
+        interval: u64,
+        /// Only show ports of this protocol (tcp or udp)
+        #[arg(short, long)]
+        protocol: Option<String>,
+    },
+    /// Follow port changes: opened, closed, restarted (new owner) and exited processes
+    Events {
+        /// One JSON object per line (NDJSON)
+        #[arg(short, long)]
+        json: bool,
+        /// Seconds between scans
+        #[arg(short, long, default_value_t = 2)]
+        interval: u64,
+    },
+    /// Stop a Docker/Podman container by ID, name or published port (e.g. :8080)
+    StopContainer {
+        #[arg(value_name = "CONTAINER")]
+        target: String,
+    },
+    /// Stop the systemd service behind a port (e.g. :8080) or a unit by name
+    StopUnit {
+        #[arg(value_name = "UNIT|:PORT")]
+        target: String,
+        /// The unit runs under `systemd --user` (detected automatically for :PORT)
+        #[arg(long)]
+        user: bool,
+        /// Also disable the unit so it does not come back on the next boot
+        #[arg(long)]
+        disable: bool,
+    },
+    /// Kill a process (PID), the owner of a port (:8080) or of a Unix socket (/run/app.sock, @abstract),
+    /// optionally with its group, session or tree
+    #[command(after_help = KILL_EXIT_CODES)]
+    Kill {
+        #[arg(value_name = "PID|:PORT|SOCKET")]
+        target: String,
+        /// process, group, session or tree (subtree from the nearest non-shell ancestor)
+        #[arg(short, long, default_value = "process")]
+        mode: String,
+        /// With :PORT, only the owner of the tcp or udp socket
+        #[arg(short, long)]
+        protocol: Option<String>,
+        /// With a PID, the start time it was listed with (field 22 of /proc/PID/stat);
+        /// a process that got the PID since is left alone
+        #[arg(long, value_name = "TICKS")]
+        start_ticks: Option<u64>,
+        #[command(flatten)]
+        kill: KillArgs,
+    },
+    /// Kill the processes behind every port of a project (root directory, or a name checked out once)
+    #[command(after_help = KILL_EXIT_CODES)]
+    KillProject {
+        project: String,
+        #[command(flatten)]
+        kill: KillArgs,
+    },
+    /// Kill every process holding a port (e.g. :8080) and wait until it is free
+    #[command(after_help = KILL_EXIT_CODES)]
+    KillPort {
+        #[arg(value_name = "PORT")]
+        target: String,
+        /// Only free the port for this protocol (tcp or udp)
+        #[arg(short, long)]
+        protocol: Option<String>,
+        /// Network namespace of the port, its inode as `list` prints it (default: host)
+        #[arg(long, value_name = "INODE")]
+        netns: Option<String>,
+        #[command(flatten)]
+        kill: KillArgs,
+    },
+    /// Open a port that speaks HTTP (e.g. :3000) in the default browser
+    Open {
+        #[arg(value_name = "PORT")]
+        target: String,
+    },
+    /// Archive the /proc inputs of the scanner for bug reports (replay with --proc-root)
+    Capture {
+        /// Output directory, or a .tar.gz/.tgz archive (default: ppkiller-capture-<timestamp>)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Read or change ~/.config/ppkiller/config.toml (ignore/include rules, source, categories)
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Kill processes by name: exact (default), glob ("python3*"), regex, or a regex over the command line
+    #[command(after_help = KILL_EXIT_CODES)]
+    KillName {
+        pattern: String,
+        /// exact, glob, regex or cmdline
+        #[arg(short = 'M', long = "match", default_value = "exact")]
+        match_mode: String,
+        #[command(flatten)]
+        kill: KillArgs,
+    },
+    /// Kill all ports, except those of containers and systemd units
+    #[command(after_help = KILL_EXIT_CODES)]
+    KillAll {
+        #[command(flatten)]
+        kill: KillArgs,
+    },
+    /// Kill exactly the processes of a confirmed plan, read as JSON from stdin (used by the menu through pkexec)
+    #[command(hide = true, after_help = KILL_EXIT_CODES)]
+    KillPlan {
+        /// Print the outcome for every process as JSON
+        #[arg(short, long)]
+        json: bool,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+}
+
+const KILL_EXIT_CODES: &str = "Exit codes: 0 all killed, 1 error, 2 invalid arguments, 3 permission denied, \
+4 already gone, 5 still running, 6 port held by another process, 7 port bound again (respawned), \
+8 protected process (init, session leader, compositor, ppkiller and its ancestors, kill.protected)";
+
+// Options of every kill command
+#[derive(Args)]
+struct KillArgs {
+    /// Print the outcome for every process (with --dry-run: the plan) as JSON
+    #[arg(short, long)]
+    json: bool,
+    /// Only list the processes and ports that would be killed
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+    /// Do not ask before killing more than one process
+    #[arg(short, long)]
+    yes: bool,
+    #[command(flatten)]
+    policy: PolicyArgs,
+}
+
+fn print_plan(plan: &app_lib::KillPlan) {
+    for p in &plan.processes {
+        let ports = if p.ports.is_empty() { String::new() } else { format!("  {}", p.ports.join(", ")) };
+        println!("  PID {:<8} {}{}", p.pid, p.name.as_deref().unwrap_or("?"), ports);
+    }
+    for p in &plan.protected {
+        println!("  skipped PID {} {}: {}", p.pid, p.name.as_deref().unwrap_or("?"), p.reason);
+    }
+}
+
+// Preview, confirm when more than one process is hit, then kill exactly the previewed plan
+fn run_bulk_kill(request: app_lib::BulkKill, args: &KillArgs, done: impl Fn(usize) -> String) -> ! {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    let policy = args.policy.resolve();
+    let plan = match app_lib::preview_kill(&request, &policy) {
+        Ok(plan) => plan,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    if args.dry_run {
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&plan).unwrap());
+        } else if plan.is_empty() && plan.protected.is_empty() {
+            println!("Nothing would be killed");
+        } else {
+            println!("Would kill {} process(es) and skip {} protected:", plan.processes.len(), plan.protected.len());
+            print_plan(&plan);
+        }
+        std::process::exit(0);
+    }
+    if plan.len() > 1 && !args.yes {
+        if !std::io::stdin().is_terminal() {
+            eprintln!("This would kill {} processes, pass --yes to go ahead or --dry-run to list them", plan.len());
+            std::process::exit(2);
+        }
+        println!("About to kill {} process(es):", plan.len());
+        print_plan(&plan);
+        print!("Continue? [y/N] ");
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        let _ = std::io::stdin().lock().read_line(&mut answer);
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            eprintln!("Aborted");
+            std::process::exit(1);
+        }
+    }
+    finish_kill(app_lib::execute_kill_plan(&plan, &policy), args.json, done);
+}
+
+// Print a kill report; the exit code is the one of its most severe outcome
+fn finish_kill(result: Result<app_lib::KillReport, String>, json: bool, done: impl Fn(usize) -> String) -> ! {
+    let report = match result {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        if report.success {
+            println!("{}", done(report.killed));
+        } else if report.killed > 0 {
+            println!("Killed {} process(es)", report.killed);
+        }
+        for result in report.results.iter().filter(|r| !r.outcome.is_success()) {
+            eprintln!("{}", result);
+        }
+    }
+    std::process::exit(report.exit_code());
+}
+
+// Overrides for the [kill] section of config.toml
+#[derive(Args)]
+struct PolicyArgs {
+    /// Signal sent first: TERM, INT, HUP, QUIT or KILL
+    #[arg(short, long)]
+    signal: Option<String>,
+    /// Seconds to wait for the processes to exit before SIGKILL
+    #[arg(short, long)]
+    timeout: Option<f64>,
+    /// Give up after the timeout instead of sending SIGKILL
+    #[arg(long)]
+    no_escalate: bool,
+    /// Also kill session leaders, compositors and kill.protected names (never init or ppkiller's ancestors)
+    #[arg(long)]
+    force: bool,
+}
+
+impl PolicyArgs {
+    fn resolve(&self) -> app_lib::TerminationPolicy {
+        let mut policy = app_lib::termination_policy();
+        if let Some(signal) = &self.signal {
+            match signal.parse::<app_lib::Signal>() {
+                Ok(signal) => policy.signal = signal,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(2);
+                }
+            }
+        }
+        if let Some(timeout) = self.timeout {
+            policy.timeout = timeout;
+        }
+        if self.no_escalate {
+            policy.escalate = false;
+        }
+        policy.force = self.force;
+        if let Err(err) = policy.validate() {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+        policy
+    }
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print a key (e.g. ignore.ports), or the whole config
+    Get {
+        key: Option<String>,
+    },
+    /// Set a key, lists as TOML arrays or comma-separated: `set include.ports 80,8000-8100`
+    Set {
+        key: String,
+        value: String,
+    },
+    /// Open the config in $VISUAL/$EDITOR and check it afterwards
+    Edit,
 }
 
 fn main() {
@@ -35,9 +347,23 @@ fn main() {
         let cli = Cli::try_parse();
         
         if let Ok(cli) = cli {
+            if let Some(source) = &cli.source {
+                if let Err(err) = app_lib::set_socket_source(source) {
+                    eprintln!("{}", err);
+                    std::process::exit(2);
+                }
+            }
+            if let Some(root) = &cli.proc_root {
+                if let Err(err) = app_lib::set_proc_root(root) {
+                    eprintln!("{}", err);
+                    std::process::exit(2);
+                }
+            }
             match cli.command {
                 Some(Commands::Waybar) => {
-                    let ports = app_lib::get_ports_list();
+                    // Polled every few seconds: no protocol probes, no health connections, the queue check only
+                    let mut ports = app_lib::get_ports_list();
+                    app_lib::check_ports_queue(&mut ports);
                     let processes = app_lib::get_processes_list();
                     
                     // Get top 10 processes by CPU or memory
@@ -66,8 +392,13 @@ fn main() {
                     tooltip.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
                     tooltip.push_str(&format!("<b>Active Ports: {}</b>\n", port_count));
                     for p in &ports {
-                        tooltip.push_str(&format!("<span color='#a6e3a1'></span>  <b>:{}</b> {} <span color='#6c7086'>(PID: {})</span>\n", 
-                            p.port, p.process_name.as_deref().unwrap_or("unknown"), p.pid.unwrap_or(0)));
+                        let (color, note) = match p.health.as_ref().map(|h| h.status) {
+                            Some(app_lib::HealthStatus::Hung) => ("#f38ba8", " HUNG"),
+                            Some(app_lib::HealthStatus::Slow) => ("#f9e2af", " slow"),
+                            _ => ("#a6e3a1", ""),
+                        };
+                        tooltip.push_str(&format!("<span color='{}'></span>  <b>:{}</b>/{} {} <span color='#6c7086'>{} (PID: {})</span><span color='{}'>{}</span>\n", 
+                            color, p.port, p.protocol, escape_markup(port_name(p)), p.service_name.as_deref().unwrap_or(&p.category), p.pid.unwrap_or(0), color, note));
                     }
                     tooltip.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
                     tooltip.push_str(&format!("<b>Top Processes (by CPU/Memory):</b>\n"));
@@ -80,31 +411,327 @@ fn main() {
                             format!("{:.1}MB", mem)
                         };
                         tooltip.push_str(&format!("<span color='#f9e2af'>󰍛</span>  <b>{}</b> CPU: {:.1}% Mem: {} <span color='#6c7086'>(PID: {})</span>\n", 
-                            p.name, cpu, mem_display, p.pid));
+                            escape_markup(&p.display_name), cpu, mem_display, p.pid));
                     }
                     
+                    // Worst port wins, so the bar can turn red when something is deadlocked
+                    let statuses: Vec<_> = ports.iter().filter_map(|p| p.health.as_ref().map(|h| h.status)).collect();
+                    let class = if statuses.contains(&app_lib::HealthStatus::Hung) {
+                        "hung"
+                    } else if statuses.contains(&app_lib::HealthStatus::Slow) {
+                        "slow"
+                    } else {
+                        "active"
+                    };
                     println!("{}", serde_json::json!({
-                        "text": text, "tooltip": tooltip.trim_end(), "class": "active"
+                        "text": text, "tooltip": tooltip.trim_end(), "class": class
                     }));
                     return;
                 }
-                Some(Commands::List { json }) => {
-                    let ports = app_lib::get_ports_list();
+                Some(Commands::List { json, unix: true, protocol, netns, category, group_by }) => {
+                    reject_with_unix(&[("--protocol", protocol.is_some()), ("--netns", netns.is_some()), ("--category", category.is_some()), ("--group-by", group_by.is_some())]);
+                    let sockets = app_lib::get_unix_sockets_list();
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&sockets).unwrap());
+                    } else {
+                        for s in sockets {
+                            println!("Socket: {} ({}), PID: {:?}, Process: {:?}", s.path, s.socket_type, s.pid, s.process_name);
+                        }
+                    }
+                    return;
+                }
+                Some(Commands::List { json, protocol, netns, category, group_by: Some(key), .. }) => {
+                    if key != "project" {
+                        eprintln!("Unknown group '{}' (expected project)", key);
+                        std::process::exit(2);
+                    }
+                    let ports = app_lib::get_ports_list_filtered(&port_filter(protocol, netns, category));
+                    let groups = group_by_project(ports);
+                    if json {
+                        let groups: Vec<_> = groups.iter().map(|(project, root, branch, ports)| serde_json::json!({
+                            "project": project, "project_root": root, "git_branch": branch, "ports": ports
+                        })).collect();
+                        println!("{}", serde_json::to_string_pretty(&groups).unwrap());
+                    } else {
+                        for (project, root, branch, ports) in groups {
+                            match (&project, &root) {
+                                (Some(project), Some(root)) => println!("{} [{}] {}", project, branch.as_deref().unwrap_or("-"), root),
+                                _ => println!("(no project)"),
+                            }
+                            for p in ports {
+                                println!("    Port: {}/{}, PID: {:?}, Process: {}", p.address(), p.protocol, p.pid, port_name(&p));
+                            }
+                        }
+                    }
+                    return;
+                }
+                Some(Commands::List { json, protocol, netns, category, .. }) => {
+                    let mut ports = app_lib::get_ports_list_filtered(&port_filter(protocol, netns, category));
+                    app_lib::check_ports_health(&mut ports);
                     if json {
                         println!("{}", serde_json::to_string_pretty(&ports).unwrap());
                     } else {
                         for p in ports {
-                            println!("Port: {}, PID: {:?}, Process: {:?}", p.port, p.pid, p.process_name);
+                            let netns = if p.namespaced { format!(", Netns: {}", p.netns) } else { String::new() };
+                            let service = p.service_name.as_ref().map(|s| format!(" {}", s)).unwrap_or_default();
+                            println!("Port: {}/{}, PID: {:?}, Process: {}, Category: {}{}{}", p.address(), p.protocol, p.pid, port_name(&p), p.category, service, netns);
+                            if let Some(cwd) = &p.cwd {
+                                println!("    Cwd: {}", cwd);
+                            }
+                            if let Some(health) = p.health.as_ref().filter(|h| h.status != app_lib::HealthStatus::Healthy) {
+                                println!("    Health: {} ({})", health.status.as_str(), health.reason.as_deref().unwrap_or("-"));
+                            }
+                            if let Some(guess) = &p.protocol_guess {
+                                match &p.protocol_detail {
+                                    Some(detail) => println!("    Speaks: {} ({})", guess, detail),
+                                    None => println!("    Speaks: {}", guess),
+                                }
+                            }
+                            if let Some(socket) = &p.socket_unit {
+                                println!("    Socket: {} ({}), activates {}", socket, p.systemd_scope.as_deref().unwrap_or("system"), p.systemd_unit.as_deref().unwrap_or("-"));
+                            } else if let Some(unit) = &p.systemd_unit {
+                                println!("    Unit: {} ({}, Restart={})", unit, p.systemd_scope.as_deref().unwrap_or("system"), p.restart_policy.as_deref().unwrap_or("?"));
+                            }
+                            if let Some(project) = &p.project {
+                                println!("    Project: {} [{}]", project, p.git_branch.as_deref().unwrap_or("-"));
+                            }
+                            if !p.ancestors.is_empty() {
+                                let chain: Vec<String> = p.ancestors.iter().map(|a| format!("{} ({})", a.name, a.pid)).collect();
+                                println!("    Parents: {}", chain.join(" <- "));
+                            }
+                            for owner in p.owners.iter().skip(1) {
+                                println!("    PID: {}, Process: {:?}, Role: {}", owner.pid, owner.process_name, owner.role);
+                            }
                         }
                     }
                     return;
                 }
-                Some(Commands::KillAll) => {
-                    app_lib::kill_all_ports();
+                Some(Commands::Connections { json, samples, interval }) => {
+                    let mut connections = app_lib::get_connections_list();
+                    for _ in 1..samples {
+                        std::thread::sleep(std::time::Duration::from_secs(interval));
+                        connections = app_lib::get_connections_list();
+                    }
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&connections).unwrap());
+                    } else {
+                        for c in connections {
+                            println!("Port: {}:{}, PID: {:?}, Process: {:?}, ESTABLISHED: {}, TIME_WAIT: {}, CLOSE_WAIT: {}{}",
+                                c.bind_address, c.port, c.pid, c.process_name, c.established, c.time_wait, c.close_wait,
+                                if c.close_wait_growing { " (growing, possible leak)" } else { "" });
+                            for peer in c.peers {
+                                println!("    {} x{}", peer.address, peer.connections);
+                            }
+                        }
+                    }
+                    return;
+                }
+                Some(Commands::Watch { interval, protocol }) => {
+                    run_watch(interval.max(1), &port_filter(protocol, None, None));
+                    return;
+                }
+                Some(Commands::Events { json, interval }) => {
+                    use std::io::Write;
+                    let mut watcher = app_lib::PortWatcher::new();
+                    let stdout = std::io::stdout();
+                    loop {
+                        std::thread::sleep(std::time::Duration::from_secs(interval.max(1)));
+                        for event in watcher.poll() {
+                            let line = if json {
+                                serde_json::to_string(&event).unwrap()
+                            } else {
+                                describe_event(&event)
+                            };
+                            // Stop quietly when the reader goes away (e.g. piped into head)
+                            let mut out = stdout.lock();
+                            if writeln!(out, "{}", line).and_then(|_| out.flush()).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+                Some(Commands::StopContainer { target }) => {
+                    let container_id = match target.strip_prefix(':') {
+                        Some(port) => app_lib::get_ports_list()
+                            .into_iter()
+                            .find(|p| p.port == port && p.container_id.is_some())
+                            .and_then(|p| p.container_id),
+                        None => Some(target.clone()),
+                    };
+                    let Some(container_id) = container_id else {
+                        eprintln!("No container publishes port {}", target);
+                        std::process::exit(1);
+                    };
+                    match app_lib::stop_container_by_id(&container_id) {
+                        Ok(msg) => println!("{}", msg),
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            std::process::exit(1);
+                        }
+                    }
+                    return;
+                }
+                Some(Commands::StopUnit { target, user, disable }) => {
+                    let unit = match target.strip_prefix(':') {
+                        Some(port) => {
+                            let Ok(port) = port.parse::<u16>() else {
+                                eprintln!("Invalid port '{}'", target);
+                                std::process::exit(2);
+                            };
+                            app_lib::systemd_unit_for_port(port)
+                        }
+                        None => Some((target.clone(), user)),
+                    };
+                    let Some((unit, user)) = unit else {
+                        eprintln!("No systemd service holds port {}", target);
+                        std::process::exit(1);
+                    };
+                    let result = if disable {
+                        app_lib::disable_systemd_unit(&unit, user)
+                    } else {
+                        app_lib::stop_systemd_unit(&unit, user)
+                    };
+                    match result {
+                        Ok(msg) => println!("{}", msg),
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            std::process::exit(1);
+                        }
+                    }
                     return;
                 }
-                Some(Commands::Menu) => {
-                    run_menu();
+                Some(Commands::Kill { target, mode, protocol, start_ticks, kill }) => {
+                    let mode = match mode.parse::<app_lib::KillMode>() {
+                        Ok(mode) => mode,
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            std::process::exit(2);
+                        }
+                    };
+                    // A port pins the owner found by the scan, a bare PID is taken as it is now
+                    let pid = target.parse::<i32>().ok();
+                    // 0 and negative PIDs address process groups, never a single process
+                    if pid.is_some_and(|pid| pid <= 0) {
+                        eprintln!("Invalid PID '{}'", target);
+                        std::process::exit(2);
+                    }
+                    if start_ticks.is_some() && pid.is_none() {
+                        eprintln!("--start-ticks only goes with a PID, ports and sockets are pinned by the scan");
+                        std::process::exit(2);
+                    }
+                    let found = match target.strip_prefix(':') {
+                        Some(port) => port_owner(port, protocol.as_deref()),
+                        // Unix socket path, "@name" for abstract ones
+                        None if target.starts_with('/') || target.starts_with('@') => unix_socket_owner(&target),
+                        None => pid.map(|pid| (pid, start_ticks)),
+                    };
+                    let Some((pid, start_ticks)) = found else {
+                        eprintln!("No process found for '{}'", target);
+                        std::process::exit(1);
+                    };
+                    let request = app_lib::BulkKill::Tree { pid, mode, start_ticks };
+                    run_bulk_kill(request, &kill, |killed| format!("Killed {} process(es)", killed));
+                }
+                Some(Commands::KillProject { project, kill }) => {
+                    let request = app_lib::BulkKill::Project { project: project.clone() };
+                    run_bulk_kill(request, &kill, |killed| format!("Killed {} process(es) of {}", killed, project));
+                }
+                Some(Commands::KillPort { target, protocol, netns, kill }) => {
+                    let Ok(port) = target.trim_start_matches(':').parse::<u16>() else {
+                        eprintln!("Invalid port '{}'", target);
+                        std::process::exit(2);
+                    };
+                    let netns = netns.filter(|ns| ns != "host");
+                    if let Some(ns) = netns.as_deref().filter(|ns| ns.parse::<u64>().is_err()) {
+                        eprintln!("Invalid network namespace '{}' (expected host or an inode)", ns);
+                        std::process::exit(2);
+                    }
+                    let request = app_lib::BulkKill::Port { port, protocol, netns };
+                    run_bulk_kill(request, &kill, |killed| format!("Port {} freed, killed {} process(es)", port, killed));
+                }
+                Some(Commands::Open { target }) => {
+                    let Ok(port) = target.trim_start_matches(':').parse::<u16>() else {
+                        eprintln!("Invalid port '{}'", target);
+                        std::process::exit(2);
+                    };
+                    let ports = app_lib::get_ports_list_probed();
+                    let Some(url) = ports.iter().filter(|p| p.port == port.to_string()).find_map(|p| p.url()) else {
+                        eprintln!("Nothing on port {} answers HTTP", port);
+                        std::process::exit(1);
+                    };
+                    if let Err(err) = app_lib::open_url(&url) {
+                        eprintln!("{}", err);
+                        std::process::exit(1);
+                    }
+                    println!("Opened {}", url);
+                    return;
+                }
+                Some(Commands::Capture { output }) => {
+                    let output = output.unwrap_or_else(|| {
+                        let ts = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_secs())
+                            .unwrap_or(0);
+                        format!("ppkiller-capture-{}", ts)
+                    });
+                    match app_lib::capture_proc(&output) {
+                        Ok(path) => println!("Capture written to {}", path.display()),
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            std::process::exit(1);
+                        }
+                    }
+                    return;
+                }
+                Some(Commands::Config { action }) => {
+                    let result = match action {
+                        ConfigAction::Get { key } => app_lib::config_get(key.as_deref()).map(|value| println!("{}", value.trim_end())),
+                        ConfigAction::Set { key, value } => app_lib::config_set(&key, &value).map(|path| println!("{} updated", path.display())),
+                        ConfigAction::Edit => app_lib::config_edit().map(|path| println!("{} saved", path.display())),
+                    };
+                    if let Err(err) = result {
+                        eprintln!("{}", err);
+                        std::process::exit(1);
+                    }
+                    return;
+                }
+                Some(Commands::KillName { pattern, match_mode, kill }) => {
+                    let mode = match match_mode.parse::<app_lib::MatchMode>() {
+                        Ok(mode) => mode,
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            std::process::exit(2);
+                        }
+                    };
+                    let request = app_lib::BulkKill::Name { name: pattern.clone(), mode };
+                    run_bulk_kill(request, &kill, |killed| format!("Killed {} process(es) matching {}", killed, pattern));
+                }
+                Some(Commands::KillAll { kill }) => {
+                    run_bulk_kill(app_lib::BulkKill::All, &kill, |killed| format!("Killed {} process(es)", killed));
+                }
+                Some(Commands::KillPlan { json, policy }) => {
+                    use std::io::Read;
+
+                    // No planning here: root would see other ports than the user who confirmed
+                    let mut input = String::new();
+                    let plan = std::io::stdin()
+                        .read_to_string(&mut input)
+                        .map_err(|e| e.to_string())
+                        .and_then(|_| serde_json::from_str::<app_lib::KillPlan>(&input).map_err(|e| format!("Invalid kill plan: {}", e)));
+                    let plan = match plan {
+                        Ok(plan) => plan,
+                        Err(err) => {
+                            eprintln!("{}", err);
+                            std::process::exit(2);
+                        }
+                    };
+                    finish_kill(app_lib::execute_kill_plan(&plan, &policy.resolve()), json, |killed| format!("Killed {} process(es)", killed));
+                }
+                Some(Commands::Menu { protocol, category, netns, unix }) => {
+                    if unix {
+                        reject_with_unix(&[("--protocol", protocol.is_some()), ("--netns", netns.is_some()), ("--category", category.is_some())]);
+                    }
+                    run_menu(&port_filter(protocol, netns, category), unix);
                     return;
                 }
                 None => {}
@@ -116,8 +743,294 @@ fn main() {
     app_lib::run();
 }
 
-fn run_menu() {
-    let ports = app_lib::get_ports_list();
+static INTERRUPTED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, std::sync::atomic::Ordering::SeqCst);
+}
+
+// "3d 4h", "2h 05m", "5m 02s", "12s"
+fn format_uptime(seconds: u64) -> String {
+    match seconds {
+        s if s >= 86400 => format!("{}d {}h", s / 86400, s % 86400 / 3600),
+        s if s >= 3600 => format!("{}h {:02}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m {:02}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
+
+fn run_watch(interval: u64, filter: &app_lib::PortFilter) {
+    use std::io::Write;
+    use std::sync::atomic::Ordering;
+
+    // Ctrl-C only sets a flag, the loop restores the terminal before exiting
+    unsafe {
+        libc::signal(libc::SIGINT, on_interrupt as *const () as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, on_interrupt as *const () as libc::sighandler_t);
+    }
+
+    let key = |p: &app_lib::PortInfo| format!("{}/{}/{}/{}", p.netns, p.protocol, p.bind_address, p.port);
+    let mut previous: Vec<app_lib::PortInfo> = Vec::new();
+    let mut first = true;
+    let mut stdout = std::io::stdout();
+
+    // Alternate screen and hidden cursor, like top
+    let _ = write!(stdout, "\x1b[?1049h\x1b[?25l");
+
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        let ports = app_lib::get_ports_list_filtered(filter);
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let mut frame = String::from("\x1b[H\x1b[2J");
+        frame.push_str(&format!("\x1b[1mPP Killer\x1b[0m  {} ports  every {}s  Ctrl-C to quit\n\n", ports.len(), interval));
+        frame.push_str(&format!("\x1b[2m  {:<7} {:<5} {:<24} {:<8} {:<9} {}\x1b[0m\n", "PORT", "PROTO", "ADDRESS", "PID", "UP", "PROCESS"));
+
+        let old_keys: std::collections::HashSet<String> = previous.iter().map(key).collect();
+        let new_keys: std::collections::HashSet<String> = ports.iter().map(key).collect();
+
+        let row = |p: &app_lib::PortInfo, marker: &str| {
+            let up = p.started_at.map(|s| format_uptime(now.saturating_sub(s))).unwrap_or_else(|| "-".to_string());
+            let pid = p.pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".to_string());
+            format!("{} {:<7} {:<5} {:<24} {:<8} {:<9} {}", marker, p.port, p.protocol, p.bind_address, pid, up, port_name(p))
+        };
+
+        for p in &ports {
+            if !first && !old_keys.contains(&key(p)) {
+                frame.push_str(&format!("\x1b[32m{}\x1b[0m\n", row(p, "+")));
+            } else {
+                frame.push_str(&row(p, " "));
+                frame.push('\n');
+            }
+        }
+        // Closed since the last refresh, shown once
+        for p in previous.iter().filter(|p| !new_keys.contains(&key(p))) {
+            frame.push_str(&format!("\x1b[31m{}\x1b[0m\n", row(p, "-")));
+        }
+
+        if write!(stdout, "{}", frame).and_then(|_| stdout.flush()).is_err() {
+            break;
+        }
+        previous = ports;
+        first = false;
+
+        // Sleep in small steps so Ctrl-C is handled right away
+        for _ in 0..interval * 10 {
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+    }
+
+    let _ = write!(stdout, "\x1b[?25h\x1b[?1049l");
+    let _ = stdout.flush();
+}
+
+fn describe_event(event: &app_lib::PortEvent) -> String {
+    use app_lib::PortEvent;
+    match event {
+        PortEvent::PortOpened { port } => format!("opened  {}/{} {} (PID {})", port.address(), port.protocol, port_name(port), port.pid.unwrap_or(0)),
+        PortEvent::PortClosed { port } => format!("closed  {}/{} {}", port.address(), port.protocol, port_name(port)),
+        PortEvent::OwnerChanged { port, old_pid, new_pid } => format!("restart {}/{} {} (PID {} -> {})",
+            port.address(), port.protocol, port_name(port), old_pid.unwrap_or(0), new_pid.unwrap_or(0)),
+        PortEvent::ProcessExited { pid, process_name } => format!("exited  {} (PID {})", process_name.as_deref().unwrap_or("unknown"), pid),
+    }
+}
+
+type ProjectGroup = (Option<String>, Option<String>, Option<String>, Vec<app_lib::PortInfo>);
+
+// One group per project root, ports without a project last
+fn group_by_project(ports: Vec<app_lib::PortInfo>) -> Vec<ProjectGroup> {
+    let mut groups: Vec<ProjectGroup> = Vec::new();
+    for p in ports {
+        match groups.iter_mut().find(|g| g.1 == p.project_root) {
+            Some(group) => group.3.push(p),
+            None => groups.push((p.project.clone(), p.project_root.clone(), p.git_branch.clone(), vec![p])),
+        }
+    }
+    groups.sort_by_key(|g| (g.1.is_none(), g.0.clone(), g.1.clone()));
+    groups
+}
+
+// "node vite" rather than the 15-character comm
+fn port_name(p: &app_lib::PortInfo) -> &str {
+    p.display_name.as_deref().or(p.process_name.as_deref()).unwrap_or("unknown")
+}
+
+// Command lines end up in Pango markup (rofi rows, Waybar tooltip)
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// Filters from the command line, invalid values exit 2 rather than matching everything
+fn port_filter(protocol: Option<String>, netns: Option<String>, category: Option<String>) -> app_lib::PortFilter {
+    let filter = app_lib::PortFilter { protocol, netns, category };
+    if let Err(err) = filter.validate() {
+        eprintln!("{}", err);
+        std::process::exit(2);
+    }
+    filter
+}
+
+// --unix lists Unix domain sockets, the port filters mean nothing there
+fn reject_with_unix(flags: &[(&str, bool)]) {
+    let given: Vec<&str> = flags.iter().filter(|(_, given)| *given).map(|(flag, _)| *flag).collect();
+    if !given.is_empty() {
+        eprintln!("{} cannot be combined with --unix", given.join(", "));
+        std::process::exit(2);
+    }
+}
+
+// The owner of a listed port; several owners (tcp and udp, containers) have to be told apart
+fn port_owner(port: &str, protocol: Option<&str>) -> Option<(i32, Option<u64>)> {
+    let rows: Vec<app_lib::PortInfo> = app_lib::get_ports_list()
+        .into_iter()
+        .filter(|p| p.port == port && p.pid.is_some())
+        .filter(|p| protocol.map(|pr| pr.eq_ignore_ascii_case(&p.protocol)).unwrap_or(true))
+        .collect();
+    let mut owners: Vec<(i32, Option<u64>)> = rows.iter().filter_map(|p| Some((p.pid?, p.start_ticks))).collect();
+    owners.sort_unstable();
+    owners.dedup();
+    if owners.len() > 1 {
+        eprintln!("Several processes listen on port {}:", port);
+        for p in &rows {
+            let netns = if p.namespaced { format!(" (netns {})", p.netns) } else { String::new() };
+            eprintln!("  {}/{}{}  PID {} {}", p.address(), p.protocol, netns, p.pid.unwrap_or(0), port_name(p));
+        }
+        eprintln!("Pass --protocol, kill one by PID or free the port with kill-port");
+        std::process::exit(2);
+    }
+    owners.pop()
+}
+
+// The process listening on a Unix socket, pinned by its start time
+fn unix_socket_owner(path: &str) -> Option<(i32, Option<u64>)> {
+    let mut owners: Vec<(i32, Option<u64>)> = app_lib::get_unix_sockets_list()
+        .into_iter()
+        .filter(|s| s.path == path)
+        .filter_map(|s| Some((s.pid?, s.start_ticks)))
+        .collect();
+    owners.sort_unstable();
+    owners.dedup();
+    if owners.len() > 1 {
+        let pids: Vec<String> = owners.iter().map(|(pid, _)| pid.to_string()).collect();
+        eprintln!("Several processes listen on {} (PID {}), kill one by PID", path, pids.join(", "));
+        std::process::exit(2);
+    }
+    owners.pop()
+}
+
+// What selecting a menu row does; rofi hands back the row's index, never its text, so a
+// process called "CTR" or "UNIT" cannot pass for another kind of row
+enum MenuAction {
+    None,
+    OpenGui,
+    OpenUrl(String),
+    KillAll,
+    StopContainer(String),
+    StopUnit { unit: String, user: bool },
+    // Several holders: free the port instead of killing one PID
+    FreePort { port: u16, protocol: String, netns: Option<String> },
+    // start_ticks pins the listed process, not whatever has its PID once pkexec asked for the password
+    Kill { pid: i32, start_ticks: Option<u64> },
+}
+
+#[derive(Default)]
+struct Menu {
+    input: String,
+    actions: Vec<MenuAction>,
+}
+
+impl Menu {
+    // One line of rofi input, ending in '\n'
+    fn row(&mut self, action: MenuAction, line: &str) {
+        self.input.push_str(line);
+        self.actions.push(action);
+    }
+}
+
+// Rows without a visible owner (another user's or namespace's process) have nothing to kill
+fn kill_action(pid: Option<i32>, start_ticks: Option<u64>) -> MenuAction {
+    match pid {
+        Some(pid) => MenuAction::Kill { pid, start_ticks },
+        None => MenuAction::None,
+    }
+}
+
+fn pid_label(pid: Option<i32>) -> String {
+    pid.map_or_else(|| "no visible PID".to_string(), |pid| format!("PID {}", pid))
+}
+
+// Second rofi prompt listing exactly what a bulk kill would hit; the plan once confirmed
+fn confirm_in_rofi(request: &app_lib::BulkKill) -> Option<app_lib::KillPlan> {
+    use std::io::Write;
+
+    let plan = match app_lib::preview_kill(request, &app_lib::termination_policy()) {
+        Ok(plan) if !plan.is_empty() => plan,
+        _ => return None,
+    };
+    let mut mesg = String::new();
+    for p in &plan.processes {
+        mesg.push_str(&format!("PID {}  <b>{}</b>  {}\n", p.pid, escape_markup(p.name.as_deref().unwrap_or("?")), p.ports.join(", ")));
+    }
+    for p in &plan.protected {
+        mesg.push_str(&format!("<span color='#6c7086'>skipped PID {} {} ({})</span>\n", p.pid, escape_markup(p.name.as_deref().unwrap_or("?")), p.reason));
+    }
+    let yes = format!("Kill {} process(es)", plan.len());
+    let Ok(mut child) = Command::new("rofi")
+        .args(["-dmenu", "-p", "Confirm", "-i", "-mesg", mesg.trim_end()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+    else {
+        return None;
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(format!("Cancel\n{}\n", yes).as_bytes());
+    }
+    let confirmed = child.wait_with_output().map(|o| String::from_utf8_lossy(&o.stdout).trim() == yes).unwrap_or(false);
+    confirmed.then_some(plan)
+}
+
+// Root kills the confirmed plan through `pkexec ppkiller kill-plan`, nothing is planned again as root;
+// the outcome is shown in a rofi message
+fn kill_plan_as_root(app: &str, plan: &app_lib::KillPlan) {
+    use std::io::Write;
+
+    let Ok(mut child) = Command::new("pkexec")
+        .args([app, "kill-plan", "--json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    else {
+        return;
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(serde_json::to_string(plan).unwrap_or_default().as_bytes());
+    }
+    let Ok(output) = child.wait_with_output() else {
+        return;
+    };
+    let message = match serde_json::from_slice::<app_lib::KillReport>(&output.stdout) {
+        Ok(report) if report.success => format!("Killed {} process(es)", report.killed),
+        Ok(report) if report.killed > 0 => format!("Killed {} process(es)\n{}", report.killed, report.errors()),
+        Ok(report) => report.errors(),
+        // The password prompt was dismissed, or kill-plan refused the plan
+        Err(_) => match String::from_utf8_lossy(&output.stderr).trim() {
+            "" => "Nothing was killed".to_string(),
+            err => err.to_string(),
+        },
+    };
+    let _ = Command::new("rofi").args(["-e", &message]).status();
+}
+
+fn run_menu(filter: &app_lib::PortFilter, unix: bool) {
+    let ports = if unix { Vec::new() } else { app_lib::get_ports_list_filtered(filter) };
+    let sockets = if unix { app_lib::get_unix_sockets_list() } else { Vec::new() };
     let processes = app_lib::get_processes_list();
     
     // Get top 10 processes by CPU/memory
@@ -133,28 +1046,68 @@ fn run_menu() {
     });
     let top_10: Vec<_> = top_processes.iter().take(10).map(|(p, _, _)| p).collect();
     
-    let mut input = String::new();
-    input.push_str("󰄬  Open PP Killer GUI                             ⌘O\n");
-    input.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
-    input.push_str("<b>󰠵 PORTS</b>                                    <span color='#6c7086'>Tab 1</span>\n");
-    input.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+    let mut menu = Menu::default();
+    menu.row(MenuAction::OpenGui, "󰄬  Open PP Killer GUI                             ⌘O\n");
+    menu.row(MenuAction::None, "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+    if unix {
+        menu.row(MenuAction::None, "<b>󰌷 UNIX SOCKETS</b>                             <span color='#6c7086'>Tab 1</span>\n");
+    } else {
+        menu.row(MenuAction::None, "<b>󰠵 PORTS</b>                                    <span color='#6c7086'>Tab 1</span>\n");
+    }
+    menu.row(MenuAction::None, "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
     
-    if ports.is_empty() {
-        input.push_str("  <span color='#6c7086'>No active ports</span>\n");
+    if unix {
+        if sockets.is_empty() {
+            menu.row(MenuAction::None, "  <span color='#6c7086'>No unix sockets</span>\n");
+        }
+        // Selecting a socket kills its owner, like a port row
+        for s in &sockets {
+            menu.row(kill_action(s.pid, s.start_ticks), &format!("  <span color='#94e2d5'>󰌷</span>  <b>{}</b> <span color='#6c7086'>{}</span>  {:<15}  <span color='#6c7086'>{}</span>\n",
+                escape_markup(&s.path), s.socket_type, escape_markup(s.process_name.as_deref().unwrap_or("unknown")), pid_label(s.pid)));
+        }
+    } else if ports.is_empty() {
+        menu.row(MenuAction::None, "  <span color='#6c7086'>No active ports</span>\n");
     } else {
         for p in &ports {
-            let name = p.process_name.as_deref().unwrap_or("unknown");
-            input.push_str(&format!("  <span color='#a6e3a1'></span>  <b>:{}</b>                    {:<15}  <span color='#6c7086'>PID {}</span>\n", 
-                p.port, name, p.pid.unwrap_or(0)));
+            // Container ports are stopped through the engine, not by killing the proxy
+            if let Some(container) = &p.container_name {
+                menu.row(MenuAction::StopContainer(p.container_id.clone().unwrap_or_else(|| container.clone())), &format!("  <span color='#89b4fa'>󰡨</span>  <b>:{}</b> <span color='#6c7086'>{}</span>                {:<15}  <span color='#6c7086'>CTR {}</span>\n",
+                    p.port, p.protocol, container, container));
+                continue;
+            }
+            let name = escape_markup(port_name(p));
+            // systemd restarts killed services, stop the unit instead
+            if let Some(unit) = p.socket_unit.as_ref().or(p.systemd_unit.as_ref()) {
+                let scope = if p.systemd_scope.as_deref() == Some("user") { " user" } else { "" };
+                menu.row(MenuAction::StopUnit { unit: unit.clone(), user: !scope.is_empty() }, &format!("  <span color='#cba6f7'>󰒓</span>  <b>:{}</b> <span color='#6c7086'>{}</span>                {:<15}  <span color='#6c7086'>UNIT {}{}</span>\n",
+                    p.port, p.protocol, name, unit, scope));
+                continue;
+            }
+            // Several holders: selecting the line frees the port instead of killing one PID
+            if p.owners.len() > 1 {
+                let netns = if p.namespaced { format!(" NS {}", p.netns) } else { String::new() };
+                menu.row(MenuAction::FreePort { port: p.port.parse().unwrap_or(0), protocol: p.protocol.clone(), netns: p.namespaced.then(|| p.netns.clone()) }, &format!("  <span color='#a6e3a1'></span>  <b>:{}</b> <span color='#6c7086'>{}</span>                {:<15}  <span color='#6c7086'>{} +{} HOLDERS{}</span>\n",
+                    p.port, p.protocol, name, pid_label(p.pid), p.owners.len() - 1, netns));
+                continue;
+            }
+            menu.row(kill_action(p.pid, p.start_ticks), &format!("  <span color='#a6e3a1'></span>  <b>:{}</b> <span color='#6c7086'>{}</span>                {:<15}  <span color='#6c7086'>{}</span>\n",
+                p.port, p.protocol, name, pid_label(p.pid)));
+        }
+        // Web servers get a second line that opens them in the browser
+        for p in &ports {
+            if let Some(url) = p.url() {
+                menu.row(MenuAction::OpenUrl(url.clone()), &format!("  <span color='#89dceb'>󰖟</span>  <b>:{}</b> Open in browser                <span color='#6c7086'>URL {}</span>\n",
+                    p.port, url));
+            }
         }
     }
     
-    input.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
-    input.push_str("<b>󰍛 PROCESSES</b>                               <span color='#6c7086'>Tab 2</span>\n");
-    input.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+    menu.row(MenuAction::None, "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+    menu.row(MenuAction::None, "<b>󰍛 PROCESSES</b>                               <span color='#6c7086'>Tab 2</span>\n");
+    menu.row(MenuAction::None, "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
     
     if top_10.is_empty() {
-        input.push_str("  <span color='#6c7086'>No processes</span>\n");
+        menu.row(MenuAction::None, "  <span color='#6c7086'>No processes</span>\n");
     } else {
         for p in top_10 {
             let cpu = p.cpu.parse::<f64>().unwrap_or(0.0);
@@ -164,15 +1117,15 @@ fn run_menu() {
             } else {
                 format!("{:.1}MB", mem)
             };
-            input.push_str(&format!("  <span color='#f9e2af'>󰍛</span>  <b>{}</b>  CPU: {:.1}%  Mem: {}  <span color='#6c7086'>PID {}</span>\n", 
-                p.name, cpu, mem_display, p.pid));
+            menu.row(MenuAction::Kill { pid: p.pid, start_ticks: p.start_ticks }, &format!("  <span color='#f9e2af'>󰍛</span>  <b>{}</b>  CPU: {:.1}%  Mem: {}  <span color='#6c7086'>PID {}</span>\n", 
+                escape_markup(&p.display_name), cpu, mem_display, p.pid));
         }
     }
     
-    input.push_str("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
-    input.push_str("󰑐  Refresh                                       ⌘R\n");
-    input.push_str("󰦢  <span color='#f38ba8'>Kill All Ports</span>                              ⌘K\n");
-    input.push_str("󰈆  Quit                                          ⌘Q\n");
+    menu.row(MenuAction::None, "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+    menu.row(MenuAction::None, "󰑐  Refresh                                       ⌘R\n");
+    menu.row(MenuAction::KillAll, "󰦢  <span color='#f38ba8'>Kill All Ports</span>                              ⌘K\n");
+    menu.row(MenuAction::None, "󰈆  Quit                                          ⌘Q\n");
 
     let rofi_theme = r#"
         * {
@@ -223,7 +1176,7 @@ fn run_menu() {
     "#;
 
     let rofi = Command::new("rofi")
-        .args(["-dmenu", "-p", "", "-i", "-markup-rows", "-theme-str", rofi_theme])
+        .args(["-dmenu", "-p", "", "-i", "-markup-rows", "-format", "i", "-theme-str", rofi_theme])
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .spawn();
@@ -231,11 +1184,15 @@ fn run_menu() {
     if let Ok(mut child) = rofi {
         use std::io::Write;
         if let Some(mut stdin) = child.stdin.take() {
-            let _ = stdin.write_all(input.as_bytes());
+            let _ = stdin.write_all(menu.input.as_bytes());
         }
 
         let output = child.wait_with_output().expect("failed read rofi");
-        let selected = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        // -1 for text typed in that matches no row
+        let selected = String::from_utf8_lossy(&output.stdout).trim().parse::<usize>().ok();
+        let Some(action) = selected.and_then(|i| menu.actions.get(i)) else {
+            return;
+        };
 
         // Find AppImage path
         let appimage_path = std::env::var("APPIMAGE")
@@ -256,20 +1213,40 @@ fn run_menu() {
             })
             .unwrap_or_else(|_| "ppkiller".to_string());
         
-        if selected.contains("Open PP Killer") {
-            // Try to launch AppImage, fallback to command
-            if appimage_path.ends_with(".AppImage") {
-                let _ = Command::new(&appimage_path).spawn();
-            } else {
+        match action {
+            MenuAction::None => {}
+            MenuAction::OpenGui => {
                 let _ = Command::new(&appimage_path).spawn();
             }
-        } else if selected.contains("Kill All") {
-            let _ = Command::new("pkexec").args([&appimage_path, "kill-all"]).status();
-        } else if selected.contains("PID") {
-            let re = Regex::new(r"PID (\d+)").unwrap();
-            if let Some(caps) = re.captures(&selected) {
-                let pid = caps.get(1).unwrap().as_str();
-                let _ = Command::new("pkexec").args(["kill", "-9", pid]).status();
+            MenuAction::OpenUrl(url) => {
+                let _ = app_lib::open_url(url);
+            }
+            MenuAction::KillAll => {
+                if let Some(plan) = confirm_in_rofi(&app_lib::BulkKill::All) {
+                    kill_plan_as_root(&appimage_path, &plan);
+                }
+            }
+            MenuAction::StopContainer(container) => {
+                let _ = Command::new(&appimage_path).args(["stop-container", container]).status();
+            }
+            MenuAction::StopUnit { unit, user: true } => {
+                let _ = Command::new(&appimage_path).args(["stop-unit", unit, "--user"]).status();
+            }
+            MenuAction::StopUnit { unit, user: false } => {
+                let _ = Command::new("pkexec").args([&appimage_path, "stop-unit", unit]).status();
+            }
+            MenuAction::FreePort { port, protocol, netns } => {
+                let request = app_lib::BulkKill::Port { port: *port, protocol: Some(protocol.clone()), netns: netns.clone() };
+                if let Some(plan) = confirm_in_rofi(&request) {
+                    kill_plan_as_root(&appimage_path, &plan);
+                }
+            }
+            MenuAction::Kill { pid, start_ticks } => {
+                let mut args = vec![appimage_path.clone(), "kill".to_string(), pid.to_string()];
+                if let Some(ticks) = start_ticks {
+                    args.extend(["--start-ticks".to_string(), ticks.to_string()]);
+                }
+                let _ = Command::new("pkexec").args(&args).status();
             }
         }
     }
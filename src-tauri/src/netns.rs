@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::proc_root::{list_pids, proc_path};
+
+// A network namespace and the procfs directory that shows its sockets
+#[derive(Debug, Clone)]
+pub(crate) struct NetNamespace {
+    // Inode of the namespace, e.g. "4026531840"
+    pub id: String,
+    // "/proc/net" for our own namespace, "/proc/[pid]/net" for the others
+    pub net_dir: String,
+    pub is_host: bool,
+}
+
+// "net:[4026531840]" -> "4026531840"
+fn read_netns(pid: &str) -> Option<String> {
+    let link = fs::read_link(proc_path(&format!("{}/ns/net", pid))).ok()?;
+    let link = link.to_str()?;
+    Some(link.strip_prefix("net:[")?.strip_suffix(']')?.to_string())
+}
+
+// The namespace we run in; its sockets are always visible through /proc/net, even under hidepid
+pub(crate) fn current_namespace() -> NetNamespace {
+    let own = read_netns("self").unwrap_or_default();
+    let host = read_netns("1").unwrap_or_else(|| own.clone());
+    NetNamespace {
+        is_host: own == host,
+        id: own,
+        net_dir: proc_path("net"),
+    }
+}
+
+// Every network namespace we can see, each listed once with one member process.
+// The host namespace is the one PID 1 lives in.
+pub(crate) fn network_namespaces() -> Vec<NetNamespace> {
+    let current = current_namespace();
+    let own = current.id.clone();
+    let host = read_netns("1").unwrap_or_else(|| own.clone());
+
+    let mut namespaces = vec![current];
+
+    let mut members: HashMap<String, i32> = HashMap::new();
+    for pid in list_pids() {
+        if let Some(ns) = read_netns(&pid.to_string()) {
+            if ns != own {
+                members.entry(ns).or_insert(pid);
+            }
+        }
+    }
+
+    for (id, pid) in members {
+        namespaces.push(NetNamespace {
+            is_host: id == host,
+            id,
+            net_dir: proc_path(&format!("{}/net", pid)),
+        });
+    }
+
+    namespaces
+}
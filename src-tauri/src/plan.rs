@@ -0,0 +1,356 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::config::glob_match;
+use crate::process_tree::ProcessTree;
+use crate::protect::Protection;
+use crate::terminate::{self, KillOutcome, KillReport, KillResult, Target, TerminationPolicy};
+use crate::{cmdline, get_pids_for_inodes, get_ports_impl, port_holders, sources, KillMode};
+
+// How BulkKill::Name compares
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    // The process name as it is, e.g. "node"
+    #[default]
+    Exact,
+    // Process name with * wildcards, e.g. "python3*"
+    Glob,
+    // Regex over the process name
+    Regex,
+    // Regex over the full command line, e.g. "vite.*--port 5173"
+    Cmdline,
+}
+
+impl std::str::FromStr for MatchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "exact" => Ok(MatchMode::Exact),
+            "glob" => Ok(MatchMode::Glob),
+            "regex" => Ok(MatchMode::Regex),
+            "cmdline" => Ok(MatchMode::Cmdline),
+            _ => Err(format!("Unknown match mode '{}' (expected exact, glob, regex or cmdline)", s)),
+        }
+    }
+}
+
+// What a bulk kill is asked to hit
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BulkKill {
+    // Every listed port, as `ppkiller kill-all`
+    All,
+    // Processes matching `name` the way `mode` says
+    Name {
+        name: String,
+        #[serde(default)]
+        mode: MatchMode,
+    },
+    // Every process holding a port of the project, by name or root directory
+    Project { project: String },
+    // Every process holding the port in one network namespace, the host's unless `netns` names another
+    Port {
+        port: u16,
+        protocol: Option<String>,
+        #[serde(default)]
+        netns: Option<String>,
+    },
+    // A process with its group, session or tree
+    Tree { pid: i32, mode: KillMode, start_ticks: Option<u64> },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PlannedKill {
+    pub pid: i32,
+    pub name: Option<String>,
+    // "8080/tcp" for every listening socket the process holds
+    pub ports: Vec<String>,
+    // Pins the previewed process, a PID reused in the meantime is left alone
+    pub start_ticks: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProtectedProcess {
+    pub pid: i32,
+    pub name: Option<String>,
+    pub reason: String,
+}
+
+// The exact processes a bulk kill hits; previews show it, execute() kills only these
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct KillPlan {
+    pub processes: Vec<PlannedKill>,
+    // Matched, but left alone, see protect.rs
+    #[serde(default)]
+    pub protected: Vec<ProtectedProcess>,
+    // Port kills check afterwards whether a supervisor bound the port again
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub netns: Option<String>,
+}
+
+impl KillPlan {
+    pub fn len(&self) -> usize {
+        self.processes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Listening "port/protocol" of every process, not filtered by the ignore rules
+fn ports_by_pid() -> HashMap<i32, Vec<String>> {
+    let records = sources::scan_listening();
+    let inodes: HashSet<i32> = records.iter().filter(|r| r.pids.is_empty()).map(|r| r.entry.inode).collect();
+    let inode_pid_map = get_pids_for_inodes(&inodes);
+
+    let mut ports: HashMap<i32, Vec<String>> = HashMap::new();
+    for record in records {
+        let port = format!("{}/{}", record.entry.local_port, record.protocol);
+        let pids = if record.pids.is_empty() {
+            inode_pid_map.get(&record.entry.inode).cloned().unwrap_or_default()
+        } else {
+            record.pids
+        };
+        for pid in pids {
+            let list = ports.entry(pid).or_default();
+            if !list.contains(&port) {
+                list.push(port.clone());
+            }
+        }
+    }
+    ports
+}
+
+// (pid, process name) -> whether BulkKill::Name hits it
+type NameMatcher = Box<dyn Fn(i32, &str) -> bool>;
+type NameTest = Box<dyn Fn(&str) -> bool>;
+
+// The kernel keeps 15 bytes of the process name (TASK_COMM_LEN - 1)
+const COMM_LEN: usize = 15;
+
+// Exact, glob and regex test the name, Cmdline goes to name_matcher
+fn name_test(name: &str, mode: MatchMode) -> Result<NameTest, String> {
+    let pattern = name.to_string();
+    Ok(match mode {
+        MatchMode::Exact => Box::new(move |n| n == pattern),
+        MatchMode::Glob => Box::new(move |n| glob_match(&pattern, n)),
+        MatchMode::Regex | MatchMode::Cmdline => {
+            let re = Regex::new(name).map_err(|e| format!("Invalid pattern '{}': {}", name, e))?;
+            Box::new(move |n| re.is_match(n))
+        }
+    })
+}
+
+fn name_matcher(name: &str, mode: MatchMode) -> Result<NameMatcher, String> {
+    let test = name_test(name, mode)?;
+    if mode == MatchMode::Cmdline {
+        return Ok(Box::new(move |pid, _| cmdline::cmdline_of(pid).is_some_and(|c| test(&c))));
+    }
+    // A cut name ("my-long-service" for my-long-service-worker) is also tried as argv[0] names it
+    Ok(Box::new(move |pid, comm| {
+        test(comm) || (comm.len() >= COMM_LEN && cmdline::program_of(pid).is_some_and(|p| p != comm && test(&p)))
+    }))
+}
+
+// Keeps the order (parents first where it matters), drops repeats and sets protected processes aside
+fn planned(plan: &mut KillPlan, tree: &ProcessTree, protection: &Protection, pids: Vec<i32>) {
+    let mut ports = ports_by_pid();
+    for pid in pids {
+        if plan.processes.iter().any(|p| p.pid == pid) || plan.protected.iter().any(|p| p.pid == pid) {
+            continue;
+        }
+        let process = tree.processes().get(&pid);
+        let name = process.map(|p| p.name.clone());
+        if let Some(reason) = protection.reason(pid, name.as_deref()) {
+            plan.protected.push(ProtectedProcess { pid, name, reason });
+            continue;
+        }
+        plan.processes.push(PlannedKill {
+            pid,
+            name,
+            ports: ports.remove(&pid).unwrap_or_default(),
+            start_ticks: process.map(|p| p.start_ticks),
+        });
+    }
+}
+
+pub(crate) fn plan(request: &BulkKill, policy: &TerminationPolicy) -> Result<KillPlan, String> {
+    let tree = ProcessTree::load();
+    let mut plan = KillPlan::default();
+
+    let pids: Vec<i32> = match request {
+        BulkKill::All => {
+            let mut pids = Vec::new();
+            for p in get_ports_impl() {
+                // Killing docker-proxy does not stop the container, leave those to stop_container
+                if p.container_id.is_some() {
+                    continue;
+                }
+                // systemd would restart them, units are stopped one at a time with stop-unit
+                if p.socket_unit.is_some() || p.systemd_unit.is_some() {
+                    continue;
+                }
+                pids.extend(p.owners.iter().map(|o| o.pid));
+            }
+            pids
+        }
+        BulkKill::Name { name, mode } => {
+            let matches = name_matcher(name, *mode)?;
+            // The pattern is in ppkiller's own command line and usually in the shell's that started it
+            let own = std::process::id() as i32;
+            let mut launchers: HashSet<i32> = tree.ancestors(own).iter().map(|l| l.pid).collect();
+            launchers.insert(own);
+            let mut pids: Vec<i32> = tree
+                .processes()
+                .iter()
+                .filter(|(pid, _)| *mode != MatchMode::Cmdline || !launchers.contains(pid))
+                .filter(|(pid, p)| matches(**pid, &p.name))
+                .map(|(pid, _)| *pid)
+                .collect();
+            if pids.is_empty() {
+                return Err(format!("No processes found matching '{}'", name));
+            }
+            pids.sort_unstable();
+            pids
+        }
+        BulkKill::Project { project } => {
+            let ports: Vec<_> = get_ports_impl().into_iter().filter(|p| p.container_id.is_none()).collect();
+            // A name only stands for its root when one checkout of it is running
+            let root = if ports.iter().any(|p| p.project_root.as_deref() == Some(project.as_str())) {
+                project.clone()
+            } else {
+                let mut roots: Vec<&str> = ports
+                    .iter()
+                    .filter(|p| p.project.as_deref() == Some(project.as_str()))
+                    .filter_map(|p| p.project_root.as_deref())
+                    .collect();
+                roots.sort_unstable();
+                roots.dedup();
+                match roots[..] {
+                    [] => return Err(format!("No ports found for project '{}'", project)),
+                    [root] => root.to_string(),
+                    _ => return Err(format!("'{}' is checked out more than once, pass the root:\n  {}", project, roots.join("\n  "))),
+                }
+            };
+            ports
+                .iter()
+                .filter(|p| p.project_root.as_deref() == Some(root.as_str()))
+                .flat_map(|p| p.owners.iter().map(|o| o.pid))
+                .collect()
+        }
+        BulkKill::Port { port, protocol, netns } => {
+            let protocol = protocol.as_deref();
+            if let Some(container) = get_ports_impl()
+                .into_iter()
+                .filter(|p| netns.as_ref().map(|id| p.netns == *id).unwrap_or(!p.namespaced))
+                .find(|p| p.port == port.to_string() && p.container_id.is_some() && protocol.map(|pr| pr.eq_ignore_ascii_case(&p.protocol)).unwrap_or(true))
+            {
+                return Err(format!("Port {} is published by container {}, stop the container instead", port, container.container_name.unwrap_or_default()));
+            }
+            // Parents first so they cannot respawn workers
+            let holders = port_holders(protocol, *port, netns.as_deref());
+            if holders.is_empty() {
+                return Err(format!("No process holds port {}", port));
+            }
+            plan.port = Some(*port);
+            plan.protocol = protocol.map(str::to_lowercase);
+            plan.netns = netns.clone();
+            holders.iter().map(|o| o.pid).collect()
+        }
+        BulkKill::Tree { pid, mode, start_ticks } => {
+            // Group, session and tree are worked out from the listed process, not from a newcomer with its PID
+            let current = tree.processes().get(pid).map(|p| p.start_ticks);
+            if start_ticks.is_some() && current.is_some() && *start_ticks != current {
+                return Err(format!("PID {} now belongs to another process, the listed one already exited", pid));
+            }
+            tree.targets(*pid, *mode)?
+        }
+    };
+
+    let protection = Protection::load(&tree, policy);
+    planned(&mut plan, &tree, &protection, pids);
+    Ok(plan)
+}
+
+// Kill exactly what the plan lists, then check a planned port was not bound again
+pub(crate) fn execute(plan: &KillPlan, policy: &TerminationPolicy) -> Result<KillReport, String> {
+    let targets: Vec<Target> = plan.processes.iter().map(|p| Target { pid: p.pid, start_ticks: p.start_ticks }).collect();
+    let mut report = terminate::terminate_targets(&targets, policy)?;
+    for process in &plan.protected {
+        report.push(KillResult { pid: Some(process.pid), outcome: KillOutcome::Protected { reason: process.reason.clone() } });
+    }
+
+    // terminate() waited for the old sockets; a supervisor may already have bound the port again
+    if let Some(port) = plan.port {
+        let respawned: Vec<i32> = port_holders(plan.protocol.as_deref(), port, plan.netns.as_deref())
+            .iter()
+            .map(|o| o.pid)
+            .filter(|pid| !plan.processes.iter().any(|p| p.pid == *pid))
+            .collect();
+        if !respawned.is_empty() {
+            let port = plan.protocol.as_ref().map(|p| format!("{}/{}", port, p)).unwrap_or_else(|| port.to_string());
+            report.push(KillResult { pid: None, outcome: KillOutcome::Respawned { port, pids: respawned } });
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_glob_and_regex_test_the_name() {
+        let exact = name_test("node", MatchMode::Exact).unwrap();
+        assert!(exact("node"));
+        assert!(!exact("nodemon"));
+
+        let glob = name_test("python3*", MatchMode::Glob).unwrap();
+        assert!(glob("python3"));
+        assert!(glob("python3.12"));
+        assert!(!glob("python"));
+
+        let regex = name_test("^(vite|esbuild)$", MatchMode::Regex).unwrap();
+        assert!(regex("vite"));
+        assert!(regex("esbuild"));
+        assert!(!regex("vitest"));
+    }
+
+    #[test]
+    fn an_invalid_regex_is_an_error() {
+        assert!(name_test("(", MatchMode::Regex).is_err());
+        assert!(name_test("(", MatchMode::Cmdline).is_err());
+        assert!(name_test("(", MatchMode::Exact).is_ok());
+    }
+
+    #[test]
+    fn long_names_match_past_the_cut_comm() {
+        // The test binary is named "<crate>-<16 hex digits>", longer than comm keeps
+        let exe = std::env::current_exe().unwrap();
+        let program = exe.file_name().unwrap().to_str().unwrap().to_string();
+        assert!(program.len() > COMM_LEN);
+        let own = std::process::id() as i32;
+        let comm = crate::proc_root::read_process(own).unwrap().name;
+        assert_eq!(comm.len(), COMM_LEN);
+
+        assert!(name_matcher(&program, MatchMode::Exact).unwrap()(own, &comm));
+        assert!(name_matcher(&format!("{}*", &program[..COMM_LEN + 1]), MatchMode::Glob).unwrap()(own, &comm));
+        assert!(name_matcher(&format!("{}$", &program[COMM_LEN..]), MatchMode::Regex).unwrap()(own, &comm));
+        assert!(!name_matcher(&format!("{}-other", program), MatchMode::Exact).unwrap()(own, &comm));
+    }
+
+    #[test]
+    fn match_modes_parse() {
+        assert_eq!("GLOB".parse::<MatchMode>(), Ok(MatchMode::Glob));
+        assert_eq!("cmdline".parse::<MatchMode>(), Ok(MatchMode::Cmdline));
+        assert!("fuzzy".parse::<MatchMode>().is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::{proc_root, PortInfo};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
+const READ_TIMEOUT: Duration = Duration::from_millis(300);
+
+// PostgreSQL SSLRequest: length 8, code 80877103; the server answers a single 'S' or 'N'
+const PG_SSL_REQUEST: [u8; 8] = [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f];
+
+// (protocol, detail)
+type Guess = (String, Option<String>);
+
+// Per listener and owner, so a listener is only probed once
+type ProbeCache = HashMap<(String, Option<i32>), Option<Guess>>;
+static CACHE: OnceLock<Mutex<ProbeCache>> = OnceLock::new();
+
+// Wildcard and loopback listeners are reached over loopback, anything else on its own address
+pub(crate) fn target(port: &PortInfo) -> Option<SocketAddr> {
+    let addr: IpAddr = port.bind_address.parse().ok()?;
+    let addr = match addr {
+        IpAddr::V4(a) if a.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(a) if a.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        a => a,
+    };
+    Some(SocketAddr::new(addr, port.port.parse().ok()?))
+}
+
+// Send `request` (if any) on a fresh connection and return whatever comes back
+fn exchange(addr: &SocketAddr, request: &[u8]) -> Option<Vec<u8>> {
+    let mut stream = TcpStream::connect_timeout(addr, CONNECT_TIMEOUT).ok()?;
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(READ_TIMEOUT));
+    if !request.is_empty() {
+        stream.write_all(request).ok()?;
+    }
+    let mut buf = vec![0u8; 1024];
+    let n = stream.read(&mut buf).ok()?;
+    buf.truncate(n);
+    if buf.is_empty() { None } else { Some(buf) }
+}
+
+fn first_line(data: &[u8]) -> String {
+    let text = String::from_utf8_lossy(data);
+    text.lines().next().unwrap_or("").trim().to_string()
+}
+
+// Protocols where the server talks first
+fn from_banner(data: &[u8]) -> Option<Guess> {
+    let line = first_line(data);
+    if line.starts_with("SSH-") {
+        return Some(("ssh".into(), Some(line)));
+    }
+    // MySQL/MariaDB handshake: 3-byte length, sequence 0, protocol 10, version string
+    if data.len() > 5 && data[3] == 0 && data[4] == 0x0a {
+        let version: Vec<u8> = data[5..].iter().take_while(|b| **b != 0).copied().collect();
+        return Some(("mysql".into(), Some(String::from_utf8_lossy(&version).into_owned())));
+    }
+    if let Some(rest) = line.strip_prefix("220") {
+        let kind = if rest.contains("SMTP") { "smtp" } else { "ftp" };
+        return Some((kind.into(), Some(rest.trim_start_matches(['-', ' ']).to_string())));
+    }
+    if line.starts_with("* OK") {
+        return Some(("imap".into(), None));
+    }
+    if line.starts_with("+OK") {
+        return Some(("pop3".into(), None));
+    }
+    None
+}
+
+// "HTTP/1.1 200 OK" + "Server: nginx" -> ("http", "200 nginx")
+fn from_http(data: &[u8]) -> Option<Guess> {
+    let text = String::from_utf8_lossy(data);
+    let status = text.lines().next()?.strip_prefix("HTTP/")?;
+    let code = status.split_whitespace().nth(1).unwrap_or("");
+    // Go and others answer plain HTTP on a TLS port with a 400 that says so
+    if text.contains("HTTP request to an HTTPS server") {
+        return Some(("tls".into(), Some("https".into())));
+    }
+    let server = text
+        .lines()
+        .find_map(|l| l.split_once(':').filter(|(k, _)| k.eq_ignore_ascii_case("server")).map(|(_, v)| v.trim().to_string()));
+    let detail = match server {
+        Some(server) => format!("{} {}", code, server),
+        None => code.to_string(),
+    };
+    Some(("http".into(), Some(detail)))
+}
+
+// Minimal OP_MSG { hello: 1, $db: "admin" }
+fn mongo_hello() -> Vec<u8> {
+    let mut doc = Vec::new();
+    doc.push(0x10);
+    doc.extend_from_slice(b"hello\0");
+    doc.extend_from_slice(&1i32.to_le_bytes());
+    doc.push(0x02);
+    doc.extend_from_slice(b"$db\0");
+    doc.extend_from_slice(&6i32.to_le_bytes());
+    doc.extend_from_slice(b"admin\0");
+    doc.push(0);
+    let doc_len = (doc.len() + 4) as i32;
+
+    let mut body = Vec::new();
+    body.extend_from_slice(&0u32.to_le_bytes()); // flagBits
+    body.push(0); // section kind 0: body document
+    body.extend_from_slice(&doc_len.to_le_bytes());
+    body.extend_from_slice(&doc);
+
+    let mut msg = Vec::new();
+    msg.extend_from_slice(&((16 + body.len()) as i32).to_le_bytes());
+    msg.extend_from_slice(&1i32.to_le_bytes()); // requestID
+    msg.extend_from_slice(&0i32.to_le_bytes()); // responseTo
+    msg.extend_from_slice(&2013i32.to_le_bytes()); // OP_MSG
+    msg.extend_from_slice(&body);
+    msg
+}
+
+// TLS record header of an alert or handshake, e.g. 15 03 01 / 16 03 03
+fn is_tls(data: &[u8]) -> bool {
+    data.len() >= 3 && (data[0] == 0x15 || data[0] == 0x16) && data[1] == 0x03
+}
+
+// A request that makes a server of `protocol` answer; empty when the server talks first,
+// None when there is no cheap and harmless one (TLS)
+pub(crate) fn ping_request(protocol: &str) -> Option<Vec<u8>> {
+    match protocol {
+        "ssh" | "mysql" | "smtp" | "ftp" | "imap" | "pop3" | "unknown" => Some(Vec::new()),
+        "postgresql" => Some(PG_SSL_REQUEST.to_vec()),
+        "redis" => Some(b"PING\r\n".to_vec()),
+        "http" => Some(b"HEAD / HTTP/1.0\r\nUser-Agent: ppkiller\r\n\r\n".to_vec()),
+        "mongodb" => Some(mongo_hello()),
+        _ => None,
+    }
+}
+
+// Cheapest and least intrusive probes first; each runs on its own connection
+fn identify(addr: &SocketAddr) -> Option<Guess> {
+    if let Some(banner) = exchange(addr, &[]) {
+        let line: String = first_line(&banner).chars().filter(|c| !c.is_control()).take(60).collect();
+        return from_banner(&banner).or(Some(("unknown".into(), Some(line))));
+    }
+
+    if let Some(reply) = exchange(addr, &PG_SSL_REQUEST) {
+        if reply == b"S" || reply == b"N" {
+            return Some(("postgresql".into(), None));
+        }
+        if is_tls(&reply) {
+            return Some(("tls".into(), None));
+        }
+    }
+
+    if let Some(reply) = exchange(addr, b"PING\r\n") {
+        if reply.starts_with(b"+PONG") || reply.starts_with(b"-NOAUTH") || reply.starts_with(b"-ERR") {
+            return Some(("redis".into(), None));
+        }
+    }
+
+    let request = format!("GET / HTTP/1.0\r\nHost: localhost:{}\r\nUser-Agent: ppkiller\r\n\r\n", addr.port());
+    if let Some(reply) = exchange(addr, request.as_bytes()) {
+        if let Some(http) = from_http(&reply) {
+            return Some(http);
+        }
+        if is_tls(&reply) {
+            return Some(("tls".into(), None));
+        }
+    }
+
+    if let Some(reply) = exchange(addr, &mongo_hello()) {
+        if reply.len() >= 16 && reply[12..16] == 2013i32.to_le_bytes() {
+            return Some(("mongodb".into(), None));
+        }
+    }
+
+    None
+}
+
+// Fill in protocol_guess for TCP listeners reachable from here, probing new ones in parallel
+pub(crate) fn annotate(ports: &mut [PortInfo]) {
+    // A capture has nothing to connect to, and other namespaces are not reachable over our loopback
+    if !proc_root::is_live() {
+        return;
+    }
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    let key = |p: &PortInfo| (format!("{}/{}/{}", p.netns, p.bind_address, p.port), p.pid);
+
+    let pending: Vec<(usize, SocketAddr)> = {
+        let cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        ports
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.protocol == "tcp" && !p.namespaced && !cache.contains_key(&key(p)))
+            .filter_map(|(i, p)| Some((i, target(p)?)))
+            .collect()
+    };
+
+    let results: Vec<(usize, Option<Guess>)> = std::thread::scope(|s| {
+        let handles: Vec<_> = pending.iter().map(|(i, addr)| s.spawn(move || (*i, identify(addr)))).collect();
+        handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    });
+
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    // Forget listeners that are gone, a new process on the same port is probed again
+    let current: std::collections::HashSet<_> = ports.iter().map(key).collect();
+    cache.retain(|k, _| current.contains(k));
+    for (i, result) in results {
+        cache.insert(key(&ports[i]), result);
+    }
+    for port in ports.iter_mut() {
+        if let Some(Some((protocol, detail))) = cache.get(&key(port)) {
+            port.protocol_guess = Some(protocol.clone());
+            port.protocol_detail = detail.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guess(protocol: &str, detail: Option<&str>) -> Option<Guess> {
+        Some((protocol.to_string(), detail.map(str::to_string)))
+    }
+
+    #[test]
+    fn banners_of_servers_that_talk_first() {
+        assert_eq!(from_banner(b"SSH-2.0-OpenSSH_9.6\r\n"), guess("ssh", Some("SSH-2.0-OpenSSH_9.6")));
+        assert_eq!(from_banner(b"J\0\0\0\x0a8.0.36\0rest"), guess("mysql", Some("8.0.36")));
+        assert_eq!(from_banner(b"220 mail.example.com ESMTP Postfix SMTP\r\n"), guess("smtp", Some("mail.example.com ESMTP Postfix SMTP")));
+        assert_eq!(from_banner(b"220-ProFTPD Server ready\r\n"), guess("ftp", Some("ProFTPD Server ready")));
+        assert_eq!(from_banner(b"* OK [CAPABILITY IMAP4rev1] Dovecot ready.\r\n"), guess("imap", None));
+        assert_eq!(from_banner(b"+OK Dovecot ready.\r\n"), guess("pop3", None));
+        assert_eq!(from_banner(b"hello"), None);
+    }
+
+    #[test]
+    fn http_status_and_server() {
+        assert_eq!(from_http(b"HTTP/1.1 200 OK\r\nserver: nginx/1.25.3\r\n\r\n"), guess("http", Some("200 nginx/1.25.3")));
+        assert_eq!(from_http(b"HTTP/1.0 404 File not found\r\nContent-Length: 0\r\n\r\n"), guess("http", Some("404")));
+        assert_eq!(
+            from_http(b"HTTP/1.0 400 Bad Request\r\n\r\nClient sent an HTTP request to an HTTPS server.\n"),
+            guess("tls", Some("https"))
+        );
+        assert_eq!(from_http(b"SSH-2.0-OpenSSH_9.6\r\n"), None);
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+// Where procfs is read from; replaced by --proc-root to replay a `ppkiller capture`
+static PROC_ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+pub(crate) fn set_proc_root(path: PathBuf) {
+    *PROC_ROOT.write().unwrap_or_else(|e| e.into_inner()) = Some(path);
+}
+
+// True when reading the real /proc of this machine
+pub(crate) fn is_live() -> bool {
+    PROC_ROOT.read().unwrap_or_else(|e| e.into_inner()).is_none()
+}
+
+pub(crate) fn proc_root() -> PathBuf {
+    PROC_ROOT
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("/proc"))
+}
+
+// proc_path("net/tcp") -> "/proc/net/tcp" (or "<capture>/net/tcp")
+pub(crate) fn proc_path(rel: &str) -> String {
+    proc_root().join(rel).to_string_lossy().into_owned()
+}
+
+// A process as seen through /proc/[pid]/status and stat
+#[derive(Debug, Clone)]
+pub(crate) struct ProcEntry {
+    pub name: String,
+    pub uid: Option<u32>,
+    pub ppid: i32,
+    pub pgid: i32,
+    pub sid: i32,
+    // Clock ticks after boot, see started_at()
+    pub start_ticks: u64,
+    pub rss_kb: u64,
+}
+
+pub(crate) fn read_process(pid: i32) -> Option<ProcEntry> {
+    let status = fs::read_to_string(proc_path(&format!("{}/status", pid))).ok()?;
+    let mut entry = ProcEntry { name: String::new(), uid: None, ppid: 0, pgid: 0, sid: 0, start_ticks: 0, rss_kb: 0 };
+
+    for line in status.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue; };
+        let value = value.trim();
+        match key {
+            "Name" => entry.name = value.to_string(),
+            // Real, effective, saved, fs - the real UID is the owner
+            "Uid" => entry.uid = value.split_whitespace().next().and_then(|u| u.parse().ok()),
+            "PPid" => entry.ppid = value.parse().unwrap_or(0),
+            "VmRSS" => entry.rss_kb = value.trim_end_matches("kB").trim().parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+
+    // "pid (comm) state ppid pgrp session ..." - comm may contain spaces, so split after the last ')'
+    if let Ok(stat) = fs::read_to_string(proc_path(&format!("{}/stat", pid))) {
+        if let Some((_, rest)) = stat.rsplit_once(')') {
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            entry.pgid = fields.get(2).and_then(|g| g.parse().ok()).unwrap_or(0);
+            entry.sid = fields.get(3).and_then(|s| s.parse().ok()).unwrap_or(0);
+            entry.start_ticks = fields.get(19).and_then(|t| t.parse().ok()).unwrap_or(0);
+        }
+    }
+    Some(entry)
+}
+
+// Boot time in seconds since the epoch, the "btime" line of /proc/stat
+fn boot_time() -> Option<u64> {
+    let stat = fs::read_to_string(proc_path("stat")).ok()?;
+    stat.lines().find_map(|l| l.strip_prefix("btime ")).and_then(|b| b.trim().parse().ok())
+}
+
+// When a process started, in seconds since the epoch
+pub(crate) fn started_at(start_ticks: u64) -> Option<u64> {
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_second <= 0 || start_ticks == 0 {
+        return None;
+    }
+    Some(boot_time()? + start_ticks / ticks_per_second as u64)
+}
+
+// Every PID directory under the proc root
+pub(crate) fn list_pids() -> Vec<i32> {
+    let mut pids = Vec::new();
+    if let Ok(entries) = fs::read_dir(proc_root()) {
+        for entry in entries.flatten() {
+            if let Ok(pid) = entry.file_name().to_string_lossy().parse::<i32>() {
+                pids.push(pid);
+            }
+        }
+    }
+    pids
+}
+
+pub(crate) fn process_table() -> HashMap<i32, ProcEntry> {
+    list_pids()
+        .into_iter()
+        .filter_map(|pid| Some((pid, read_process(pid)?)))
+        .collect()
+}
+
+// UID -> user name from /etc/passwd, or the passwd copy stored in a capture
+pub(crate) fn user_names() -> HashMap<u32, String> {
+    let passwd = if is_live() {
+        PathBuf::from("/etc/passwd")
+    } else {
+        proc_root().join("passwd")
+    };
+
+    let mut names = HashMap::new();
+    if let Ok(content) = fs::read_to_string(passwd) {
+        for line in content.lines() {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 3 { continue; }
+            if let Ok(uid) = fields[2].parse::<u32>() {
+                names.entry(uid).or_insert_with(|| fields[0].to_string());
+            }
+        }
+    }
+    names
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::proc_root::{self, ProcEntry};
+
+// Wrappers that sit between a dev command and the real server, e.g. `npm run dev` -> `sh -c` -> `node vite`
+const SHELLS: [&str; 9] = ["sh", "bash", "dash", "zsh", "fish", "ksh", "mksh", "tcsh", "csh"];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProcessLink {
+    pub pid: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum KillMode {
+    // Only the given PID
+    #[default]
+    Process,
+    // Every process in its process group
+    Group,
+    // Every process in its session
+    Session,
+    // The subtree under the nearest non-shell ancestor
+    Tree,
+}
+
+impl std::str::FromStr for KillMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "process" | "pid" => Ok(KillMode::Process),
+            "group" | "pgid" => Ok(KillMode::Group),
+            "session" | "sid" => Ok(KillMode::Session),
+            "tree" | "subtree" => Ok(KillMode::Tree),
+            _ => Err(format!("Unknown kill mode '{}' (expected process, group, session or tree)", s)),
+        }
+    }
+}
+
+pub(crate) struct ProcessTree {
+    processes: HashMap<i32, ProcEntry>,
+    children: HashMap<i32, Vec<i32>>,
+}
+
+impl ProcessTree {
+    pub fn new(processes: HashMap<i32, ProcEntry>) -> Self {
+        let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+        for (&pid, process) in &processes {
+            children.entry(process.ppid).or_default().push(pid);
+        }
+        for list in children.values_mut() {
+            list.sort_unstable();
+        }
+        ProcessTree { processes, children }
+    }
+
+    pub fn load() -> Self {
+        Self::new(proc_root::process_table())
+    }
+
+    pub fn processes(&self) -> &HashMap<i32, ProcEntry> {
+        &self.processes
+    }
+
+    // Parent first, up to (not including) PID 1
+    pub fn ancestors(&self, pid: i32) -> Vec<ProcessLink> {
+        let mut chain = Vec::new();
+        let mut current = pid;
+        // Bounded walk, a broken capture must not loop forever
+        for _ in 0..64 {
+            let Some(parent) = self.processes.get(&current).map(|p| p.ppid) else { break; };
+            if parent <= 1 {
+                break;
+            }
+            let Some(process) = self.processes.get(&parent) else { break; };
+            chain.push(ProcessLink { pid: parent, name: process.name.clone() });
+            current = parent;
+        }
+        chain
+    }
+
+    // The process and all its descendants, parents before children
+    pub fn subtree(&self, pid: i32) -> Vec<i32> {
+        let mut result = vec![pid];
+        let mut i = 0;
+        while i < result.len() {
+            if let Some(children) = self.children.get(&result[i]) {
+                for &child in children {
+                    if !result.contains(&child) {
+                        result.push(child);
+                    }
+                }
+            }
+            i += 1;
+        }
+        result
+    }
+
+    // Climb through shell wrappers to the process that started them, but never
+    // past a session leader (the terminal's shell) or into another user's processes
+    pub fn tree_root(&self, pid: i32) -> i32 {
+        let Some(start) = self.processes.get(&pid) else { return pid; };
+        let mut root = pid;
+        for link in self.ancestors(pid) {
+            let Some(parent) = self.processes.get(&link.pid) else { break; };
+            if parent.sid == link.pid || parent.uid != start.uid {
+                break;
+            }
+            root = link.pid;
+            if !SHELLS.contains(&parent.name.as_str()) {
+                break;
+            }
+        }
+        root
+    }
+
+    // PIDs a kill in this mode would hit; never this process or its ancestors
+    pub fn targets(&self, pid: i32, mode: KillMode) -> Result<Vec<i32>, String> {
+        let process = self.processes.get(&pid).ok_or_else(|| format!("No process with PID {}", pid))?;
+
+        let mut targets = match mode {
+            KillMode::Process => vec![pid],
+            KillMode::Group | KillMode::Session => {
+                let (id, what) = if mode == KillMode::Group { (process.pgid, "process group") } else { (process.sid, "session") };
+                if id <= 1 {
+                    return Err(format!("PID {} has no {} to kill", pid, what));
+                }
+                // The leader first, then the rest
+                let mut members: Vec<i32> = self
+                    .processes
+                    .iter()
+                    .filter(|(_, p)| if mode == KillMode::Group { p.pgid == id } else { p.sid == id })
+                    .map(|(&p, _)| p)
+                    .collect();
+                members.sort_unstable_by_key(|&p| (p != id, p));
+                members
+            }
+            KillMode::Tree => self.subtree(self.tree_root(pid)),
+        };
+
+        let own = std::process::id() as i32;
+        let mut protected: Vec<i32> = self.ancestors(own).iter().map(|l| l.pid).collect();
+        protected.push(own);
+        targets.retain(|p| *p > 1 && !protected.contains(p));
+
+        if targets.is_empty() {
+            return Err(format!("Refusing to kill PID {}: it would take down ppkiller itself", pid));
+        }
+        Ok(targets)
+    }
+}
@@ -0,0 +1,107 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Checked in this order in every directory while walking up from the cwd
+const MARKERS: [&str; 5] = ["package.json", "Cargo.toml", "pyproject.toml", "go.mod", ".git"];
+
+#[derive(Debug, Clone)]
+pub(crate) struct Project {
+    pub name: String,
+    pub root: String,
+    pub branch: Option<String>,
+}
+
+// `name = "..."` inside the given TOML table, enough for Cargo.toml and pyproject.toml
+fn toml_name(content: &str, tables: &[&str]) -> Option<String> {
+    let mut in_table = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_table = tables.contains(&line);
+            continue;
+        }
+        if !in_table { continue; }
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == "name" {
+                return Some(value.trim().trim_matches('"').trim_matches('\'').to_string());
+            }
+        }
+    }
+    None
+}
+
+fn project_name(root: &Path, marker: &str) -> Option<String> {
+    let content = fs::read_to_string(root.join(marker)).ok()?;
+    match marker {
+        "package.json" => serde_json::from_str::<Value>(&content).ok()?["name"].as_str().map(|s| s.to_string()),
+        "Cargo.toml" => toml_name(&content, &["[package]"]),
+        "pyproject.toml" => toml_name(&content, &["[project]", "[tool.poetry]"]),
+        // "module github.com/acme/api" -> "api"
+        "go.mod" => content
+            .lines()
+            .find_map(|l| l.trim().strip_prefix("module "))
+            .map(|m| m.trim().rsplit('/').next().unwrap_or(m).to_string()),
+        _ => None,
+    }
+}
+
+// The git directory for a checkout; worktrees have a `.git` file pointing at it
+fn git_dir(dir: &Path) -> Option<PathBuf> {
+    let dot_git = dir.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let content = fs::read_to_string(&dot_git).ok()?;
+    let path = content.trim().strip_prefix("gitdir:")?.trim();
+    Some(dir.join(path))
+}
+
+// "ref: refs/heads/main" -> "main", a detached HEAD -> short commit hash
+fn git_branch(start: &Path) -> Option<String> {
+    let git = start.ancestors().find_map(git_dir)?;
+    let head = fs::read_to_string(git.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(reference.strip_prefix("refs/heads/").unwrap_or(reference).to_string()),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+fn find_project(cwd: &Path) -> Option<Project> {
+    for dir in cwd.ancestors() {
+        // A marker in / or the home directory itself says nothing about the project
+        if dir.parent().is_none() || std::env::var_os("HOME").map(|h| dir == Path::new(&h)).unwrap_or(false) {
+            break;
+        }
+        let Some(marker) = MARKERS.iter().find(|m| dir.join(m).exists()) else { continue; };
+        let name = project_name(dir, marker)
+            .filter(|n| !n.is_empty())
+            .or_else(|| dir.file_name().map(|n| n.to_string_lossy().into_owned()))?;
+        return Some(Project {
+            name,
+            root: dir.to_string_lossy().into_owned(),
+            branch: git_branch(dir),
+        });
+    }
+    None
+}
+
+// Owners of many ports often share a cwd, look each one up once per scan
+pub(crate) struct ProjectResolver {
+    cache: HashMap<String, Option<Project>>,
+}
+
+impl ProjectResolver {
+    pub fn new() -> Self {
+        ProjectResolver { cache: HashMap::new() }
+    }
+
+    pub fn resolve(&mut self, cwd: &str) -> Option<Project> {
+        self.cache
+            .entry(cwd.to_string())
+            .or_insert_with(|| find_project(Path::new(cwd)))
+            .clone()
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::config::glob_match;
+use crate::process_tree::ProcessTree;
+use crate::terminate::TerminationPolicy;
+
+// Display servers and compositors: killing one ends the whole graphical session
+const DISPLAY_SERVERS: [&str; 22] = [
+    "Xorg", "Xwayland", "X", "gnome-shell", "mutter", "kwin_wayland", "kwin_x11", "sway", "Hyprland", "weston",
+    "river", "wayfire", "labwc", "niri", "cosmic-comp", "hikari", "gamescope", "xfwm4", "openbox", "i3", "gdm", "sddm",
+];
+
+// Leaders of the logind sessions (sshd, login, gdm-session-worker, ...), from logind's own state files
+fn session_leaders() -> Vec<i32> {
+    let Ok(entries) = fs::read_dir("/run/systemd/sessions") else { return Vec::new(); };
+    entries
+        .flatten()
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .filter_map(|content| content.lines().find_map(|l| l.strip_prefix("LEADER="))?.trim().parse().ok())
+        .collect()
+}
+
+// Processes no kill may signal, with the reason shown instead
+pub(crate) struct Protection {
+    reasons: HashMap<i32, String>,
+    names: Vec<String>,
+    force: bool,
+}
+
+impl Protection {
+    pub fn load(tree: &ProcessTree, policy: &TerminationPolicy) -> Self {
+        let mut reasons = HashMap::new();
+        reasons.insert(1, "PID 1 (init)".to_string());
+
+        // Never ppkiller itself, nor the shell, terminal or launcher it runs in
+        let own = std::process::id() as i32;
+        reasons.insert(own, "ppkiller itself".to_string());
+        for link in tree.ancestors(own) {
+            reasons.insert(link.pid, "runs ppkiller".to_string());
+        }
+
+        if !policy.force {
+            if let Some(sid) = tree.processes().get(&own).map(|p| p.sid).filter(|s| *s > 1) {
+                reasons.entry(sid).or_insert_with(|| "session leader".to_string());
+            }
+            for leader in session_leaders() {
+                reasons.entry(leader).or_insert_with(|| "session leader".to_string());
+            }
+            for (pid, process) in tree.processes() {
+                if DISPLAY_SERVERS.contains(&process.name.as_str()) {
+                    reasons.entry(*pid).or_insert_with(|| "display server or compositor".to_string());
+                }
+            }
+        }
+        Protection { reasons, names: policy.protected.clone(), force: policy.force }
+    }
+
+    // Why the process must not be killed, None when it may
+    pub fn reason(&self, pid: i32, name: Option<&str>) -> Option<String> {
+        if let Some(reason) = self.reasons.get(&pid) {
+            return Some(reason.clone());
+        }
+        let name = name.filter(|_| !self.force)?;
+        self.names.iter().any(|pattern| glob_match(pattern, name)).then(|| "protected in config.toml".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proc_root::ProcEntry;
+
+    const SHELL: i32 = 900_001;
+    const TERMINAL: i32 = 900_002;
+    const XORG: i32 = 900_003;
+    const POSTGRES: i32 = 900_004;
+    const STRANGER: i32 = 900_005;
+
+    fn process(name: &str, ppid: i32, sid: i32) -> ProcEntry {
+        ProcEntry { name: name.into(), uid: Some(1000), ppid, pgid: sid, sid, start_ticks: 1, rss_kb: 0 }
+    }
+
+    // init -> terminal (session leader) -> shell -> this test, plus a few bystanders
+    fn tree() -> ProcessTree {
+        let own = std::process::id() as i32;
+        ProcessTree::new(HashMap::from([
+            (1, process("systemd", 0, 1)),
+            (TERMINAL, process("foot", 1, TERMINAL)),
+            (SHELL, process("bash", TERMINAL, TERMINAL)),
+            (own, process("ppkiller", SHELL, TERMINAL)),
+            (XORG, process("Xorg", 1, XORG)),
+            (POSTGRES, process("postgres", 1, POSTGRES)),
+            (STRANGER, process("node", 1, STRANGER)),
+        ]))
+    }
+
+    fn policy(force: bool) -> TerminationPolicy {
+        TerminationPolicy { protected: vec!["postgres*".into()], force, ..Default::default() }
+    }
+
+    #[test]
+    fn init_and_ppkiller_with_its_ancestors_are_protected() {
+        let protection = Protection::load(&tree(), &policy(false));
+        assert_eq!(protection.reason(1, Some("systemd")).as_deref(), Some("PID 1 (init)"));
+        assert_eq!(protection.reason(std::process::id() as i32, Some("ppkiller")).as_deref(), Some("ppkiller itself"));
+        assert_eq!(protection.reason(SHELL, Some("bash")).as_deref(), Some("runs ppkiller"));
+        assert_eq!(protection.reason(TERMINAL, Some("foot")).as_deref(), Some("runs ppkiller"));
+        assert_eq!(protection.reason(STRANGER, Some("node")), None);
+    }
+
+    #[test]
+    fn display_servers_and_configured_names_are_protected() {
+        let protection = Protection::load(&tree(), &policy(false));
+        assert_eq!(protection.reason(XORG, Some("Xorg")).as_deref(), Some("display server or compositor"));
+        assert_eq!(protection.reason(POSTGRES, Some("postgres")).as_deref(), Some("protected in config.toml"));
+        assert_eq!(protection.reason(STRANGER, Some("postgresql-17")).as_deref(), Some("protected in config.toml"));
+        assert_eq!(protection.reason(STRANGER, None), None);
+    }
+
+    #[test]
+    fn own_session_leader_is_protected() {
+        let own = std::process::id() as i32;
+        // The leader is an ancestor here, so give this process a session of its own
+        let mut processes = tree().processes().iter().map(|(pid, p)| (*pid, p.clone())).collect::<HashMap<_, _>>();
+        processes.insert(own, process("ppkiller", SHELL, STRANGER));
+        let protection = Protection::load(&ProcessTree::new(processes), &policy(false));
+        assert_eq!(protection.reason(STRANGER, Some("node")).as_deref(), Some("session leader"));
+    }
+
+    #[test]
+    fn force_keeps_only_init_and_ppkiller() {
+        let protection = Protection::load(&tree(), &policy(true));
+        assert!(protection.reason(1, Some("systemd")).is_some());
+        assert!(protection.reason(SHELL, Some("bash")).is_some());
+        assert_eq!(protection.reason(XORG, Some("Xorg")), None);
+        assert_eq!(protection.reason(POSTGRES, Some("postgres")), None);
+    }
+}
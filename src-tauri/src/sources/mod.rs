@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::NetEntry;
+
+mod netlink;
+mod procfs;
+mod ss;
+
+pub(crate) use netlink::NetlinkSource;
+pub(crate) use procfs::ProcfsSource;
+pub(crate) use ss::SsSource;
+
+// Socket options not every backend can see; None means "unknown"
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SocketOptions {
+    // Maximum accept queue (listen() backlog)
+    pub backlog: Option<u32>,
+    // Connections waiting in the accept queue right now
+    pub accept_queue: Option<u32>,
+    pub reuseport: Option<bool>,
+    pub v6only: Option<bool>,
+}
+
+// One listening socket as reported by a backend
+#[derive(Debug, Clone)]
+pub(crate) struct SocketRecord {
+    // "tcp" or "udp"
+    pub protocol: &'static str,
+    pub netns: String,
+    pub namespaced: bool,
+    pub entry: NetEntry,
+    // Owners when the backend already knows them (ss), otherwise found via get_pids_for_inodes
+    pub pids: Vec<i32>,
+    pub options: SocketOptions,
+}
+
+pub(crate) trait SocketSource {
+    fn name(&self) -> &'static str;
+
+    // False for backends that only see the network namespace we run in
+    fn all_namespaces(&self) -> bool {
+        false
+    }
+
+    // Listening TCP sockets and bound UDP sockets
+    fn listening(&self) -> Result<Vec<SocketRecord>, String>;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    #[default]
+    Procfs,
+    Netlink,
+    Ss,
+}
+
+impl std::str::FromStr for SourceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "procfs" | "proc" => Ok(SourceKind::Procfs),
+            "netlink" | "sock_diag" => Ok(SourceKind::Netlink),
+            "ss" => Ok(SourceKind::Ss),
+            _ => Err(format!("Unknown socket source '{}' (expected procfs, netlink or ss)", s)),
+        }
+    }
+}
+
+static SELECTED: RwLock<Option<SourceKind>> = RwLock::new(None);
+
+pub(crate) fn set_source(kind: SourceKind) {
+    *SELECTED.write().unwrap_or_else(|e| e.into_inner()) = Some(kind);
+}
+
+// Explicit choice first, then $PPKILLER_SOURCE, then config.toml, then procfs
+pub(crate) fn selected_source() -> SourceKind {
+    if let Some(kind) = *SELECTED.read().unwrap_or_else(|e| e.into_inner()) {
+        return kind;
+    }
+    std::env::var("PPKILLER_SOURCE")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .or_else(|| crate::config::load().source)
+        .unwrap_or_default()
+}
+
+fn source_for(kind: SourceKind) -> Box<dyn SocketSource> {
+    match kind {
+        SourceKind::Procfs => Box::new(ProcfsSource),
+        SourceKind::Netlink => Box::new(NetlinkSource),
+        SourceKind::Ss => Box::new(SsSource),
+    }
+}
+
+// Scan with the selected backend, falling back to procfs if it fails.
+// Other network namespaces always come from procfs, see SocketSource::all_namespaces
+pub(crate) fn scan_listening() -> Vec<SocketRecord> {
+    // A captured /proc can only be replayed through procfs
+    let kind = if crate::proc_root::is_live() { selected_source() } else { SourceKind::Procfs };
+    let source = source_for(kind);
+    let mut records = match source.listening() {
+        // netlink and ss miss containers and sandboxes, their sockets are read from procfs
+        Ok(mut records) if !source.all_namespaces() => {
+            records.extend(procfs::other_namespaces());
+            records
+        }
+        Ok(records) => records,
+        Err(err) => {
+            log::warn!("{} socket source failed ({}), falling back to procfs", source.name(), err);
+            ProcfsSource.listening().unwrap_or_default()
+        }
+    };
+    mark_reuseport(&mut records);
+    records
+}
+
+// Two TCP listeners on the exact same address and port are only possible with SO_REUSEPORT.
+// UDP sockets share a port through SO_REUSEADDR as well (mDNS on 5353), those stay unknown
+fn mark_reuseport(records: &mut [SocketRecord]) {
+    let mut counts: HashMap<(String, std::net::IpAddr, u16), usize> = HashMap::new();
+    for r in records.iter().filter(|r| r.protocol == "tcp") {
+        *counts.entry((r.netns.clone(), r.entry.local_addr, r.entry.local_port)).or_default() += 1;
+    }
+    for r in records.iter_mut().filter(|r| r.protocol == "tcp") {
+        if counts[&(r.netns.clone(), r.entry.local_addr, r.entry.local_port)] > 1 {
+            r.options.reuseport = Some(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(protocol: &'static str, addr: &str, port: u16) -> SocketRecord {
+        SocketRecord {
+            protocol,
+            netns: "4026531840".into(),
+            namespaced: false,
+            entry: NetEntry {
+                local_addr: addr.parse().unwrap(),
+                local_port: port,
+                remote_addr: "0.0.0.0".parse().unwrap(),
+                state: if protocol == "tcp" { "0A" } else { "07" }.into(),
+                inode: 0,
+                rx_queue: 0,
+            },
+            pids: Vec::new(),
+            options: SocketOptions::default(),
+        }
+    }
+
+    #[test]
+    fn duplicate_tcp_listeners_mean_reuseport() {
+        let mut records = vec![record("tcp", "0.0.0.0", 8080), record("tcp", "0.0.0.0", 8080), record("tcp", "127.0.0.1", 8080)];
+        mark_reuseport(&mut records);
+        assert_eq!(records[0].options.reuseport, Some(true));
+        assert_eq!(records[1].options.reuseport, Some(true));
+        assert_eq!(records[2].options.reuseport, None);
+    }
+
+    #[test]
+    fn shared_udp_ports_stay_unknown() {
+        let mut records = vec![record("udp", "0.0.0.0", 5353), record("udp", "0.0.0.0", 5353)];
+        mark_reuseport(&mut records);
+        assert!(records.iter().all(|r| r.options.reuseport.is_none()));
+    }
+}
@@ -0,0 +1,226 @@
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::{SocketOptions, SocketRecord, SocketSource};
+use crate::{netns, NetEntry};
+
+// From linux/sock_diag.h, linux/inet_diag.h and linux/netlink.h
+const NETLINK_SOCK_DIAG: i32 = 4;
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLM_F_REQUEST: u16 = 0x01;
+const NLM_F_DUMP: u16 = 0x300;
+const INET_DIAG_SKV6ONLY: u16 = 11;
+const TCP_LISTEN: u32 = 10;
+const TCP_CLOSE: u32 = 7;
+
+const NLMSG_HDR_LEN: usize = 16;
+// inet_diag_req_v2: family, protocol, ext, pad, states + inet_diag_sockid (48 bytes)
+const REQ_LEN: usize = 56;
+// inet_diag_msg: family, state, timer, retrans + sockid + expires, rqueue, wqueue, uid, inode
+const MSG_LEN: usize = 72;
+
+// Queries NETLINK_SOCK_DIAG directly; also reports backlog and IPV6_V6ONLY
+pub(crate) struct NetlinkSource;
+
+struct NetlinkSocket(i32);
+
+impl Drop for NetlinkSocket {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+struct DiagMsg {
+    local_addr: IpAddr,
+    local_port: u16,
+    accept_queue: u32,
+    backlog: u32,
+    inode: u32,
+    v6only: Option<bool>,
+}
+
+fn align4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn read_u16(buf: &[u8], at: usize) -> u16 {
+    u16::from_ne_bytes([buf[at], buf[at + 1]])
+}
+
+fn read_u32(buf: &[u8], at: usize) -> u32 {
+    u32::from_ne_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+fn parse_diag_msg(payload: &[u8]) -> Option<DiagMsg> {
+    if payload.len() < MSG_LEN {
+        return None;
+    }
+
+    let family = payload[0] as i32;
+    // inet_diag_sockid starts at 4: sport (be16), dport (be16), src[16], dst[16], ...
+    let local_port = u16::from_be_bytes([payload[4], payload[5]]);
+    let local_addr = match family {
+        libc::AF_INET => IpAddr::V4(Ipv4Addr::new(payload[8], payload[9], payload[10], payload[11])),
+        libc::AF_INET6 => {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(&payload[8..24]);
+            IpAddr::V6(Ipv6Addr::from(bytes))
+        }
+        _ => return None,
+    };
+
+    // For listeners the kernel reports sk_ack_backlog and sk_max_ack_backlog here
+    let accept_queue = read_u32(payload, 56);
+    let backlog = read_u32(payload, 60);
+    let inode = read_u32(payload, 68);
+
+    // Attributes follow the fixed header
+    let mut v6only = None;
+    let mut at = MSG_LEN;
+    while at + 4 <= payload.len() {
+        let len = read_u16(payload, at) as usize;
+        let kind = read_u16(payload, at + 2);
+        if len < 4 || at + len > payload.len() {
+            break;
+        }
+        if kind == INET_DIAG_SKV6ONLY && len > 4 {
+            v6only = Some(payload[at + 4] != 0);
+        }
+        at += align4(len);
+    }
+
+    Some(DiagMsg { local_addr, local_port, accept_queue, backlog, inode, v6only })
+}
+
+fn dump(family: u8, protocol: u8, states: u32) -> Result<Vec<DiagMsg>, String> {
+    let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, NETLINK_SOCK_DIAG) };
+    if fd < 0 {
+        return Err(format!("socket: {}", std::io::Error::last_os_error()));
+    }
+    let socket = NetlinkSocket(fd);
+
+    let mut request = Vec::with_capacity(NLMSG_HDR_LEN + REQ_LEN);
+    request.extend_from_slice(&((NLMSG_HDR_LEN + REQ_LEN) as u32).to_ne_bytes());
+    request.extend_from_slice(&SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+    request.extend_from_slice(&(NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+    request.extend_from_slice(&1u32.to_ne_bytes()); // seq
+    request.extend_from_slice(&0u32.to_ne_bytes()); // pid, 0 = kernel
+    request.extend_from_slice(&[family, protocol, 0, 0]);
+    request.extend_from_slice(&states.to_ne_bytes());
+    request.resize(NLMSG_HDR_LEN + REQ_LEN, 0); // zeroed inet_diag_sockid matches everything
+
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    let sent = unsafe {
+        libc::sendto(
+            socket.0,
+            request.as_ptr() as *const libc::c_void,
+            request.len(),
+            0,
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if sent < 0 {
+        return Err(format!("sendto: {}", std::io::Error::last_os_error()));
+    }
+
+    let mut messages = Vec::new();
+    let mut buf = vec![0u8; 32 * 1024];
+    loop {
+        let received = unsafe { libc::recv(socket.0, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+        if received < 0 {
+            return Err(format!("recv: {}", std::io::Error::last_os_error()));
+        }
+        let received = received as usize;
+        if received == 0 {
+            return Ok(messages);
+        }
+
+        let mut at = 0;
+        while at + NLMSG_HDR_LEN <= received {
+            let len = read_u32(&buf, at) as usize;
+            let kind = read_u16(&buf, at + 4);
+            if len < NLMSG_HDR_LEN || at + len > received {
+                break;
+            }
+            match kind {
+                NLMSG_DONE => return Ok(messages),
+                NLMSG_ERROR => {
+                    let errno = i32::from_ne_bytes([buf[at + 16], buf[at + 17], buf[at + 18], buf[at + 19]]);
+                    return Err(format!("sock_diag: {}", std::io::Error::from_raw_os_error(-errno)));
+                }
+                _ => messages.extend(parse_diag_msg(&buf[at + NLMSG_HDR_LEN..at + len])),
+            }
+            at += align4(len);
+        }
+    }
+}
+
+impl SocketSource for NetlinkSource {
+    fn name(&self) -> &'static str {
+        "netlink"
+    }
+
+    // sock_diag only sees the namespace we run in
+    fn listening(&self) -> Result<Vec<SocketRecord>, String> {
+        let ns = netns::current_namespace();
+        let mut records = Vec::new();
+
+        let queries = [
+            ("tcp", libc::IPPROTO_TCP, 1 << TCP_LISTEN),
+            ("udp", libc::IPPROTO_UDP, 1 << TCP_CLOSE),
+        ];
+        for (protocol, ipproto, states) in queries {
+            for family in [libc::AF_INET, libc::AF_INET6] {
+                let messages = match dump(family as u8, ipproto as u8, states) {
+                    Ok(messages) => messages,
+                    // udp_diag is a separate module that may not be loaded
+                    Err(err) if protocol == "udp" => {
+                        log::warn!("netlink UDP dump failed: {}", err);
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                };
+
+                for msg in messages {
+                    if msg.local_port == 0 { continue; }
+                    let Ok(inode) = i32::try_from(msg.inode) else { continue; };
+                    let unspecified = if msg.local_addr.is_ipv4() {
+                        IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+                    } else {
+                        IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+                    };
+                    let listener = protocol == "tcp";
+
+                    records.push(SocketRecord {
+                        protocol,
+                        netns: ns.id.clone(),
+                        namespaced: !ns.is_host,
+                        entry: NetEntry {
+                            local_addr: msg.local_addr,
+                            local_port: msg.local_port,
+                            remote_addr: unspecified,
+                            state: format!("{:02X}", if listener { TCP_LISTEN } else { TCP_CLOSE }),
+                            inode,
+                            rx_queue: msg.accept_queue,
+                        },
+                        pids: Vec::new(),
+                        options: SocketOptions {
+                            backlog: listener.then_some(msg.backlog),
+                            accept_queue: listener.then_some(msg.accept_queue),
+                            reuseport: None,
+                            v6only: msg.v6only,
+                        },
+                    });
+                }
+            }
+        }
+
+        Ok(records)
+    }
+}
@@ -0,0 +1,49 @@
+use super::{SocketOptions, SocketRecord, SocketSource};
+use crate::{netns, scan_proc_net_tcp, scan_proc_net_udp};
+
+// Reads /proc/[pid]/net/{tcp,udp}[6] once per network namespace
+pub(crate) struct ProcfsSource;
+
+impl SocketSource for ProcfsSource {
+    fn name(&self) -> &'static str {
+        "procfs"
+    }
+
+    fn all_namespaces(&self) -> bool {
+        true
+    }
+
+    fn listening(&self) -> Result<Vec<SocketRecord>, String> {
+        Ok(listening_in(&netns::network_namespaces()))
+    }
+}
+
+// Sockets of every namespace but ours, for the backends that only see the one we run in
+pub(crate) fn other_namespaces() -> Vec<SocketRecord> {
+    let own = netns::current_namespace().id;
+    let others: Vec<_> = netns::network_namespaces().into_iter().filter(|ns| ns.id != own).collect();
+    listening_in(&others)
+}
+
+fn listening_in(namespaces: &[netns::NetNamespace]) -> Vec<SocketRecord> {
+    let mut records = Vec::new();
+    for ns in namespaces {
+        for (protocol, file) in [("tcp", "tcp"), ("tcp", "tcp6"), ("udp", "udp"), ("udp", "udp6")] {
+            let path = format!("{}/{}", ns.net_dir, file);
+            let entries = if protocol == "tcp" { scan_proc_net_tcp(&path) } else { scan_proc_net_udp(&path) };
+            records.extend(entries.into_iter().map(|entry| SocketRecord {
+                protocol,
+                netns: ns.id.clone(),
+                namespaced: !ns.is_host,
+                pids: Vec::new(),
+                // procfs has the accept queue of listeners but not the backlog
+                options: SocketOptions {
+                    accept_queue: (protocol == "tcp").then_some(entry.rx_queue),
+                    ..SocketOptions::default()
+                },
+                entry,
+            }));
+        }
+    }
+    records
+}
@@ -0,0 +1,100 @@
+use regex::Regex;
+use std::net::{IpAddr, Ipv6Addr};
+use std::process::Command;
+
+use super::{SocketOptions, SocketRecord, SocketSource};
+use crate::{netns, NetEntry};
+
+// Shells out to `ss`; useful where /proc is mounted with hidepid or similar restrictions
+pub(crate) struct SsSource;
+
+// "127.0.0.53%lo:53", "[::1]:631", "*:5353" -> address and port
+fn parse_local(field: &str) -> Option<(IpAddr, u16)> {
+    let (host, port) = field.rsplit_once(':')?;
+    let port = port.parse::<u16>().ok()?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let host = host.split('%').next().unwrap_or(host);
+    let addr = if host == "*" {
+        IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+    } else {
+        host.parse().ok()?
+    };
+    Some((addr, port))
+}
+
+impl SocketSource for SsSource {
+    fn name(&self) -> &'static str {
+        "ss"
+    }
+
+    fn listening(&self) -> Result<Vec<SocketRecord>, String> {
+        let output = Command::new("ss")
+            .args(["-H", "-ltunpe"])
+            .output()
+            .map_err(|e| format!("failed to execute ss: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+
+        let re_pid = Regex::new(r"pid=(\d+),").unwrap();
+        let ns = netns::current_namespace();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut records = Vec::new();
+
+        // Netid State Recv-Q Send-Q Local Peer [users:(...)] [uid:N] [ino:N] ...
+        for line in stdout.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 6 { continue; }
+
+            let (protocol, state) = match parts[0] {
+                "tcp" => ("tcp", "0A"),
+                "udp" => ("udp", "07"),
+                _ => continue,
+            };
+            let Some((local_addr, local_port)) = parse_local(parts[4]) else { continue; };
+            if local_port == 0 { continue; }
+
+            let inode = parts
+                .iter()
+                .find_map(|p| p.strip_prefix("ino:"))
+                .and_then(|i| i.parse::<i32>().ok())
+                .unwrap_or(0);
+            let v6only = parts
+                .iter()
+                .find_map(|p| p.strip_prefix("v6only:"))
+                .map(|v| v == "1");
+
+            // users:(("nginx",pid=12,fd=6),("nginx",pid=11,fd=6)) lists every holder
+            let mut pids: Vec<i32> = re_pid.captures_iter(line).filter_map(|caps| caps[1].parse().ok()).collect();
+            pids.sort_unstable();
+            pids.dedup();
+
+            // For listeners Recv-Q is the accept queue and Send-Q the backlog
+            let listener = protocol == "tcp";
+            let unspecified = IpAddr::V6(Ipv6Addr::UNSPECIFIED);
+
+            records.push(SocketRecord {
+                protocol,
+                netns: ns.id.clone(),
+                namespaced: !ns.is_host,
+                entry: NetEntry {
+                    local_addr,
+                    local_port,
+                    remote_addr: unspecified,
+                    state: state.to_string(),
+                    inode,
+                    rx_queue: parts[2].parse().unwrap_or(0),
+                },
+                pids,
+                options: SocketOptions {
+                    backlog: if listener { parts[3].parse().ok() } else { None },
+                    accept_queue: if listener { parts[2].parse().ok() } else { None },
+                    reuseport: None,
+                    v6only,
+                },
+            });
+        }
+
+        Ok(records)
+    }
+}
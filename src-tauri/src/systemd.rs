@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use zbus::blocking::Connection;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+use crate::proc_root::{self, proc_path};
+
+const DESTINATION: &str = "org.freedesktop.systemd1";
+const MANAGER_PATH: &str = "/org/freedesktop/systemd1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.systemd1.Manager";
+
+// A service unit and the manager it runs under
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct UnitRef {
+    pub name: String,
+    // Started by `systemd --user` rather than the system manager
+    pub user: bool,
+}
+
+impl UnitRef {
+    pub fn scope(&self) -> &'static str {
+        if self.user { "user" } else { "system" }
+    }
+}
+
+// "0::/system.slice/nginx.service" or
+// "0::/user.slice/user-1000.slice/user@1000.service/app.slice/vite.service"
+fn unit_from_cgroup(content: &str) -> Option<UnitRef> {
+    // cgroup v2 ("0::") or the v1 systemd hierarchy
+    let path = content
+        .lines()
+        .find_map(|l| l.strip_prefix("0::"))
+        .or_else(|| content.lines().find_map(|l| l.split_once(":name=systemd:").map(|(_, p)| p)))?;
+
+    let components: Vec<&str> = path.split('/').collect();
+    let user = components.iter().any(|c| c.starts_with("user@") && c.ends_with(".service"));
+    // Only real services: scopes are terminals, sessions and containers, and app-*.service are
+    // transient units a desktop starts an application in (app-org.kde.konsole@1a2b.service)
+    let name = components
+        .iter()
+        .rev()
+        .find(|c| c.ends_with(".service") && !c.starts_with("user@") && !c.starts_with("app-"))?;
+    Some(UnitRef { name: name.to_string(), user })
+}
+
+pub(crate) fn unit_for_pid(pid: i32) -> Option<UnitRef> {
+    let content = fs::read_to_string(proc_path(&format!("{}/cgroup", pid))).ok()?;
+    unit_from_cgroup(&content)
+}
+
+fn connect(user: bool) -> Result<Connection, String> {
+    let conn = if user { Connection::session() } else { Connection::system() };
+    conn.map_err(|e| format!("Cannot connect to the {} bus: {}", if user { "session" } else { "system" }, e))
+}
+
+fn unit_path(conn: &Connection, name: &str) -> Result<OwnedObjectPath, String> {
+    let reply = conn
+        .call_method(Some(DESTINATION), MANAGER_PATH, Some(MANAGER_INTERFACE), "GetUnit", &(name,))
+        .map_err(|e| format!("{}: {}", name, e))?;
+    reply.body().deserialize::<OwnedObjectPath>().map_err(|e| e.to_string())
+}
+
+fn property(conn: &Connection, path: &OwnedObjectPath, interface: &str, name: &str) -> Result<OwnedValue, String> {
+    let reply = conn
+        .call_method(Some(DESTINATION), path.as_ref(), Some("org.freedesktop.DBus.Properties"), "Get", &(interface, name))
+        .map_err(|e| e.to_string())?;
+    reply.body().deserialize::<OwnedValue>().map_err(|e| e.to_string())
+}
+
+fn restart_policy(conn: &Connection, path: &OwnedObjectPath) -> Result<String, String> {
+    let value = property(conn, path, "org.freedesktop.systemd1.Service", "Restart")?;
+    String::try_from(value).map_err(|e| e.to_string())
+}
+
+// None when the service has no main process (oneshot, forking without a PID file)
+fn main_pid(conn: &Connection, path: &OwnedObjectPath) -> Option<i32> {
+    let value = property(conn, path, "org.freedesktop.systemd1.Service", "MainPID").ok()?;
+    u32::try_from(value).ok().filter(|pid| *pid != 0).map(|pid| pid as i32)
+}
+
+// Units a .socket starts when a connection comes in, usually one .service
+fn triggers(conn: &Connection, path: &OwnedObjectPath) -> Vec<String> {
+    property(conn, path, "org.freedesktop.systemd1.Unit", "Triggers")
+        .ok()
+        .and_then(|v| Vec::<String>::try_from(v).ok())
+        .unwrap_or_default()
+}
+
+// An active .socket unit and the addresses it listens on
+#[derive(Debug, Clone)]
+pub(crate) struct SocketUnit {
+    pub name: String,
+    pub service: Option<String>,
+    pub user: bool,
+    // (protocol, address or None for any, port)
+    listen: Vec<(&'static str, Option<IpAddr>, u16)>,
+}
+
+impl SocketUnit {
+    pub fn scope(&self) -> &'static str {
+        if self.user { "user" } else { "system" }
+    }
+}
+
+// Listen= entries: ("Stream", "0.0.0.0:8080"), ("Datagram", "[::1]:53"), ("Stream", "8080");
+// Unix sockets, FIFOs and netlink entries are skipped
+fn parse_listen(kind: &str, address: &str) -> Option<(&'static str, Option<IpAddr>, u16)> {
+    let protocol = match kind {
+        "Stream" => "tcp",
+        "Datagram" => "udp",
+        _ => return None,
+    };
+    if let Ok(port) = address.parse::<u16>() {
+        return Some((protocol, None, port));
+    }
+    let (host, port) = address.rsplit_once(':')?;
+    let port = port.parse::<u16>().ok()?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addr = host.split('%').next()?.parse::<IpAddr>().ok()?;
+    Some((protocol, if addr.is_unspecified() { None } else { Some(addr) }, port))
+}
+
+fn list_socket_units(user: bool) -> Result<Vec<SocketUnit>, String> {
+    let conn = connect(user)?;
+    let reply = conn
+        .call_method(Some(DESTINATION), MANAGER_PATH, Some(MANAGER_INTERFACE), "ListUnits", &())
+        .map_err(|e| e.to_string())?;
+    // name, description, load, active, sub, following, path, job id, job type, job path
+    type UnitRow = (String, String, String, String, String, String, OwnedObjectPath, u32, String, OwnedObjectPath);
+    let rows: Vec<UnitRow> = reply.body().deserialize().map_err(|e| e.to_string())?;
+
+    let mut units = Vec::new();
+    for (name, _, _, active, _, _, path, ..) in rows {
+        if !name.ends_with(".socket") || active != "active" {
+            continue;
+        }
+        let listen: Vec<(String, String)> = property(&conn, &path, "org.freedesktop.systemd1.Socket", "Listen")
+            .ok()
+            .and_then(|v| Vec::<(String, String)>::try_from(v).ok())
+            .unwrap_or_default();
+        let listen: Vec<_> = listen.iter().filter_map(|(kind, address)| parse_listen(kind, address)).collect();
+        if listen.is_empty() {
+            continue;
+        }
+        let service = triggers(&conn, &path).into_iter().next();
+        units.push(SocketUnit { name, service, user, listen });
+    }
+    Ok(units)
+}
+
+// Socket units are listed once per scan, and only if some port looks socket-activated
+pub(crate) struct SocketUnitResolver {
+    units: Option<Vec<SocketUnit>>,
+}
+
+impl SocketUnitResolver {
+    pub fn new() -> Self {
+        SocketUnitResolver { units: None }
+    }
+
+    pub fn resolve(&mut self, protocol: &str, addr: IpAddr, port: u16) -> Option<SocketUnit> {
+        if !proc_root::is_live() {
+            return None;
+        }
+        let units = self.units.get_or_insert_with(|| {
+            let mut all = list_socket_units(false).unwrap_or_default();
+            all.extend(list_socket_units(true).unwrap_or_default());
+            all
+        });
+        units
+            .iter()
+            .find(|u| u.listen.iter().any(|(p, a, lp)| *p == protocol && *lp == port && a.map(|a| a == addr).unwrap_or(true)))
+            .cloned()
+    }
+}
+
+// Main PID and Restart= policy of a unit, None where the bus did not say
+type UnitState = (Option<i32>, Option<String>);
+
+// Looks units up once per scan; the buses are only touched when a port belongs to a service
+pub(crate) struct UnitResolver {
+    connections: HashMap<bool, Option<Connection>>,
+    units: HashMap<UnitRef, UnitState>,
+}
+
+impl UnitResolver {
+    pub fn new() -> Self {
+        UnitResolver { connections: HashMap::new(), units: HashMap::new() }
+    }
+
+    // The unit and its Restart= policy ("no", "on-failure", "always", ...)
+    pub fn resolve(&mut self, pid: i32) -> Option<(UnitRef, Option<String>)> {
+        let unit = unit_for_pid(pid)?;
+        // A capture has no bus to ask
+        if !proc_root::is_live() {
+            return Some((unit, None));
+        }
+        if !self.units.contains_key(&unit) {
+            let conn = self.connections.entry(unit.user).or_insert_with(|| connect(unit.user).ok());
+            let state = conn
+                .as_ref()
+                .and_then(|c| unit_path(c, &unit.name).ok().map(|path| (main_pid(c, &path), restart_policy(c, &path).ok())))
+                .unwrap_or_default();
+            self.units.insert(unit.clone(), state);
+        }
+        let (main, policy) = self.units[&unit].clone();
+
+        // Only the main process and the workers it forked are the service's; a dev server started
+        // from a shell in tmux.service shares its cgroup, but stopping tmux is not what kills it
+        if let Some(main) = main {
+            let parent = proc_root::read_process(pid).map(|p| p.ppid);
+            if pid != main && parent != Some(main) {
+                return None;
+            }
+        }
+        Some((unit, policy))
+    }
+}
+
+fn manager_call<B>(user: bool, method: &str, body: &B) -> Result<(), String>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    let conn = connect(user)?;
+    conn.call_method(Some(DESTINATION), MANAGER_PATH, Some(MANAGER_INTERFACE), method, body)
+        .map(|_| ())
+        .map_err(|e| match e {
+            zbus::Error::MethodError(name, _, _) if name.as_str().ends_with("AccessDenied") || name.as_str().ends_with("InteractiveAuthorizationRequired") => {
+                format!("Not allowed to manage system units ({}), run ppkiller as root", method)
+            }
+            e => e.to_string(),
+        })
+}
+
+fn validate(unit: &str) -> Result<(), String> {
+    if unit.is_empty() || !unit.chars().all(|c| c.is_ascii_alphanumeric() || "@_.-:\\".contains(c)) {
+        return Err(format!("Invalid unit name '{}'", unit));
+    }
+    Ok(())
+}
+
+pub(crate) fn stop_unit_impl(unit: &str, user: bool) -> Result<String, String> {
+    validate(unit)?;
+    manager_call(user, "StopUnit", &(unit, "replace"))?;
+
+    // A service started by the socket keeps the listening fd, stop it as well
+    if unit.ends_with(".socket") {
+        let conn = connect(user)?;
+        let services = unit_path(&conn, unit).map(|path| triggers(&conn, &path)).unwrap_or_default();
+        for service in &services {
+            manager_call(user, "StopUnit", &(service.as_str(), "replace"))?;
+        }
+        if !services.is_empty() {
+            return Ok(format!("Unit {} stopped along with {}", unit, services.join(", ")));
+        }
+    }
+    Ok(format!("Unit {} stopped", unit))
+}
+
+// `systemctl disable --now`: stopping alone lets the next boot (or socket) bring it back
+pub(crate) fn disable_unit_impl(unit: &str, user: bool) -> Result<String, String> {
+    validate(unit)?;
+    manager_call(user, "DisableUnitFiles", &(vec![unit], false))?;
+    manager_call(user, "Reload", &())?;
+    manager_call(user, "StopUnit", &(unit, "replace"))?;
+    Ok(format!("Unit {} disabled and stopped", unit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_and_user_services() {
+        assert_eq!(
+            unit_from_cgroup("0::/system.slice/nginx.service\n"),
+            Some(UnitRef { name: "nginx.service".into(), user: false })
+        );
+        assert_eq!(
+            unit_from_cgroup("0::/user.slice/user-1000.slice/user@1000.service/app.slice/vite.service\n"),
+            Some(UnitRef { name: "vite.service".into(), user: true })
+        );
+        // cgroup v1
+        assert_eq!(
+            unit_from_cgroup("12:pids:/system.slice/sshd.service\n1:name=systemd:/system.slice/sshd.service\n"),
+            Some(UnitRef { name: "sshd.service".into(), user: false })
+        );
+    }
+
+    #[test]
+    fn scopes_and_desktop_apps_are_not_services() {
+        assert_eq!(unit_from_cgroup("0::/user.slice/user-1000.slice/session-2.scope\n"), None);
+        assert_eq!(
+            unit_from_cgroup("0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-org.kde.konsole@0a1b2c.service\n"),
+            None
+        );
+        assert_eq!(unit_from_cgroup("0::/user.slice/user-1000.slice/user@1000.service/init.scope\n"), None);
+        assert_eq!(unit_from_cgroup("0::/\n"), None);
+    }
+}
@@ -0,0 +1,498 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::{Duration, Instant};
+
+use crate::proc_root::{self, proc_path};
+use crate::process_tree::ProcessTree;
+use crate::protect::Protection;
+use crate::sources;
+
+const POLL: Duration = Duration::from_millis(50);
+// After SIGKILL, and for the kernel to drop the sockets of exited processes
+const SETTLE: Duration = Duration::from_millis(1000);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Signal {
+    #[default]
+    Term,
+    Int,
+    Hup,
+    Quit,
+    Kill,
+}
+
+impl Signal {
+    fn number(self) -> libc::c_int {
+        match self {
+            Signal::Term => libc::SIGTERM,
+            Signal::Int => libc::SIGINT,
+            Signal::Hup => libc::SIGHUP,
+            Signal::Quit => libc::SIGQUIT,
+            Signal::Kill => libc::SIGKILL,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Signal::Term => "TERM",
+            Signal::Int => "INT",
+            Signal::Hup => "HUP",
+            Signal::Quit => "QUIT",
+            Signal::Kill => "KILL",
+        }
+    }
+}
+
+impl std::str::FromStr for Signal {
+    type Err = String;
+
+    // "TERM", "sigterm" or "15"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_uppercase();
+        match name.strip_prefix("SIG").unwrap_or(&name) {
+            "TERM" | "15" => Ok(Signal::Term),
+            "INT" | "2" => Ok(Signal::Int),
+            "HUP" | "1" => Ok(Signal::Hup),
+            "QUIT" | "3" => Ok(Signal::Quit),
+            "KILL" | "9" => Ok(Signal::Kill),
+            _ => Err(format!("Unknown signal '{}' (expected TERM, INT, HUP, QUIT or KILL)", s)),
+        }
+    }
+}
+
+// How processes are stopped: `signal` first, SIGKILL for whatever is left after `timeout` seconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct TerminationPolicy {
+    pub signal: Signal,
+    pub timeout: f64,
+    pub escalate: bool,
+    // Process names (with * wildcards) no kill may touch, on top of the built-in ones in protect.rs
+    pub protected: Vec<String>,
+    // --force: only PID 1 and ppkiller with its ancestors stay protected
+    #[serde(skip)]
+    pub force: bool,
+}
+
+impl Default for TerminationPolicy {
+    fn default() -> Self {
+        TerminationPolicy { signal: Signal::Term, timeout: 3.0, escalate: true, protected: Vec::new(), force: false }
+    }
+}
+
+impl TerminationPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.timeout.is_finite() || self.timeout < 0.0 {
+            return Err(format!("Invalid kill timeout {}", self.timeout));
+        }
+        Ok(())
+    }
+}
+
+// What a kill did to one process, or to a port once its processes were gone
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum KillOutcome {
+    // Exited after SIGKILL, the chosen signal
+    Killed,
+    // Exited within the grace period after a gentler signal
+    Exited { signal: Signal },
+    // Ignored the signal until the grace period ran out
+    NeededKill { signal: Signal },
+    PermissionDenied,
+    // Exited before it was signalled, or its PID belongs to another process by now
+    AlreadyGone,
+    // Refused without sending anything: init, ppkiller's own ancestors, the compositor, ...
+    Protected { reason: String },
+    // Alive after the last signal: no escalation, or stuck in the kernel
+    StillRunning { signal: Signal },
+    // The owners exited, but a process that inherited the socket keeps the port open;
+    // no pids when it cannot be seen
+    PortHeld { port: String, pids: Vec<i32> },
+    // Freed, then bound again by a new process, usually a supervisor restarting the service
+    Respawned { port: String, pids: Vec<i32> },
+    Failed { error: String },
+}
+
+impl KillOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, KillOutcome::Killed | KillOutcome::Exited { .. } | KillOutcome::NeededKill { .. })
+    }
+
+    // Exit code of the CLI, the most severe outcome of a report wins
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            KillOutcome::Killed | KillOutcome::Exited { .. } | KillOutcome::NeededKill { .. } => 0,
+            KillOutcome::Failed { .. } => 1,
+            KillOutcome::PermissionDenied => 3,
+            KillOutcome::AlreadyGone => 4,
+            KillOutcome::StillRunning { .. } => 5,
+            KillOutcome::PortHeld { .. } => 6,
+            KillOutcome::Respawned { .. } => 7,
+            KillOutcome::Protected { .. } => 8,
+        }
+    }
+
+    fn severity(&self) -> u8 {
+        match self {
+            KillOutcome::Killed | KillOutcome::Exited { .. } | KillOutcome::NeededKill { .. } => 0,
+            KillOutcome::AlreadyGone => 1,
+            KillOutcome::Protected { .. } => 2,
+            KillOutcome::Failed { .. } => 3,
+            KillOutcome::PermissionDenied => 4,
+            KillOutcome::StillRunning { .. } => 5,
+            KillOutcome::PortHeld { .. } => 6,
+            KillOutcome::Respawned { .. } => 7,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct KillResult {
+    // None for port outcomes
+    pub pid: Option<i32>,
+    #[serde(flatten)]
+    pub outcome: KillOutcome,
+}
+
+fn join_pids(pids: &[i32]) -> String {
+    pids.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+impl std::fmt::Display for KillResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pid = self.pid.map(|p| format!("PID {}", p)).unwrap_or_default();
+        match &self.outcome {
+            KillOutcome::Killed => write!(f, "{} killed", pid),
+            KillOutcome::Exited { signal } => write!(f, "{} exited after SIG{}", pid, signal.as_str()),
+            KillOutcome::NeededKill { signal } => write!(f, "{} ignored SIG{}, needed SIGKILL", pid, signal.as_str()),
+            KillOutcome::PermissionDenied => write!(f, "{}: permission denied", pid),
+            KillOutcome::AlreadyGone => write!(f, "{} was already gone", pid),
+            KillOutcome::Protected { reason } => write!(f, "{} is protected ({})", pid, reason),
+            KillOutcome::StillRunning { signal } => write!(f, "{} is still running after SIG{}", pid, signal.as_str()),
+            KillOutcome::PortHeld { port, pids } if pids.is_empty() => write!(f, "Port {} is still held by a process that is not visible", port),
+            KillOutcome::PortHeld { port, pids } => write!(f, "Port {} is still held by PID {}", port, join_pids(pids)),
+            KillOutcome::Respawned { port, pids } => write!(f, "Port {} was bound again by PID {}", port, join_pids(pids)),
+            KillOutcome::Failed { error } => write!(f, "{}: {}", pid, error),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct KillReport {
+    // Processes that exited because of the kill
+    pub killed: usize,
+    // Every result is killed, exited or needed_kill
+    pub success: bool,
+    pub results: Vec<KillResult>,
+}
+
+impl KillReport {
+    pub fn new(results: Vec<KillResult>) -> Self {
+        KillReport {
+            killed: results.iter().filter(|r| r.pid.is_some() && r.outcome.is_success()).count(),
+            success: results.iter().all(|r| r.outcome.is_success()),
+            results,
+        }
+    }
+
+    pub fn push(&mut self, result: KillResult) {
+        let results = std::mem::take(&mut self.results);
+        *self = KillReport::new(results.into_iter().chain(std::iter::once(result)).collect());
+    }
+
+    // The most severe outcome, None for an empty report
+    pub fn outcome(&self) -> Option<&KillOutcome> {
+        self.results.iter().map(|r| &r.outcome).max_by_key(|o| o.severity())
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.outcome().map(|o| o.exit_code()).unwrap_or(0)
+    }
+
+    // The failures, one per line; empty when everything went fine
+    pub fn errors(&self) -> String {
+        self.results.iter().filter(|r| !r.outcome.is_success()).map(|r| r.to_string()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+// A process as the caller saw it: the start time from /proc/[pid]/stat changes when the
+// PID is reused, so a listed row cannot kill whatever got its number later.
+// Without start_ticks the process found under the PID right now is taken.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Target {
+    pub pid: i32,
+    pub start_ticks: Option<u64>,
+}
+
+// Pinned by a pidfd, signals and exit waits then go to this very process even if the
+// PID is reused meanwhile. Kernels before 5.3 have no pidfds, there the start time is
+// checked again right before kill()
+struct Pinned {
+    pid: i32,
+    start_ticks: u64,
+    pidfd: Option<OwnedFd>,
+}
+
+fn errno() -> Option<i32> {
+    std::io::Error::last_os_error().raw_os_error()
+}
+
+fn start_ticks(pid: i32) -> Option<u64> {
+    proc_root::read_process(pid).map(|p| p.start_ticks)
+}
+
+fn pin(target: Target) -> Result<Pinned, KillOutcome> {
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, target.pid, 0) };
+    let pidfd = if fd >= 0 {
+        Some(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+    } else {
+        match errno() {
+            Some(libc::ESRCH) => return Err(KillOutcome::AlreadyGone),
+            Some(libc::ENOSYS) => None,
+            _ => return Err(KillOutcome::Failed { error: std::io::Error::last_os_error().to_string() }),
+        }
+    };
+    // Read after pidfd_open: if the start time still matches, the pidfd is the listed process
+    let Some(ticks) = start_ticks(target.pid) else { return Err(KillOutcome::AlreadyGone); };
+    // The listed process exited and its PID went to a newcomer
+    if target.start_ticks.is_some_and(|expected| expected != ticks) {
+        return Err(KillOutcome::AlreadyGone);
+    }
+    Ok(Pinned { pid: target.pid, start_ticks: ticks, pidfd })
+}
+
+// Gone, or a zombie waiting for its parent: either way it holds no sockets any more
+fn has_exited(process: &Pinned) -> bool {
+    if let Some(fd) = &process.pidfd {
+        // A pidfd turns readable once the process exits
+        let mut pfd = libc::pollfd { fd: fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        return unsafe { libc::poll(&mut pfd, 1, 0) } != 0;
+    }
+    let Ok(stat) = fs::read_to_string(proc_path(&format!("{}/stat", process.pid))) else { return true; };
+    let Some((_, rest)) = stat.rsplit_once(')') else { return true; };
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    fields.first() == Some(&"Z") || fields.get(19).and_then(|t| t.parse().ok()) != Some(process.start_ticks)
+}
+
+// Gone by now is fine, the exit wait sees it
+fn send(process: &Pinned, signal: Signal) -> Result<(), KillOutcome> {
+    let sent = match &process.pidfd {
+        Some(fd) => unsafe {
+            libc::syscall(libc::SYS_pidfd_send_signal, fd.as_raw_fd(), signal.number(), std::ptr::null::<libc::siginfo_t>(), 0) == 0
+        },
+        None => {
+            if start_ticks(process.pid) != Some(process.start_ticks) {
+                return Ok(());
+            }
+            unsafe { libc::kill(process.pid, signal.number()) == 0 }
+        }
+    };
+    if sent {
+        return Ok(());
+    }
+    match errno() {
+        Some(libc::ESRCH) => Ok(()),
+        Some(libc::EPERM) => Err(KillOutcome::PermissionDenied),
+        _ => Err(KillOutcome::Failed { error: std::io::Error::last_os_error().to_string() }),
+    }
+}
+
+// Wait until every process exited, returns the ones still running at the deadline
+fn wait_for_exit<'a>(processes: &[&'a Pinned], timeout: Duration) -> Vec<&'a Pinned> {
+    let start = Instant::now();
+    loop {
+        let running: Vec<&Pinned> = processes.iter().copied().filter(|p| !has_exited(p)).collect();
+        let left = timeout.saturating_sub(start.elapsed());
+        if running.is_empty() || left.is_zero() {
+            return running;
+        }
+        // Wakes up as soon as one of the pidfds is readable; without pidfds this is just a sleep
+        let mut fds: Vec<libc::pollfd> = running
+            .iter()
+            .filter_map(|p| p.pidfd.as_ref())
+            .map(|fd| libc::pollfd { fd: fd.as_raw_fd(), events: libc::POLLIN, revents: 0 })
+            .collect();
+        let wait = if fds.len() == running.len() { left } else { left.min(POLL) };
+        unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, wait.as_millis().max(1) as libc::c_int) };
+    }
+}
+
+fn socket_inodes(pid: i32) -> HashSet<i32> {
+    let Ok(fds) = fs::read_dir(proc_path(&format!("{}/fd", pid))) else { return HashSet::new(); };
+    fds.flatten()
+        .filter_map(|fd| fs::read_link(fd.path()).ok())
+        .filter_map(|target| {
+            let target = target.to_string_lossy().into_owned();
+            target.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+        })
+        .collect()
+}
+
+// Listening sockets of `pids`, as "port/protocol" by inode
+fn listening_sockets(pids: &[i32]) -> Vec<(i32, String)> {
+    let inodes: HashSet<i32> = pids.iter().flat_map(|p| socket_inodes(*p)).collect();
+    if inodes.is_empty() {
+        return Vec::new();
+    }
+    sources::scan_listening()
+        .into_iter()
+        .filter(|r| inodes.contains(&r.entry.inode))
+        .map(|r| (r.entry.inode, format!("{}/{}", r.entry.local_port, r.protocol)))
+        .collect()
+}
+
+// Signal every target by the policy, escalate if needed, then wait until the listening
+// sockets they held are gone from the table. Err only when nothing could be tried.
+pub(crate) fn terminate_targets(targets: &[Target], policy: &TerminationPolicy) -> Result<KillReport, String> {
+    if !proc_root::is_live() {
+        return Err("Cannot kill processes while replaying a capture".to_string());
+    }
+    policy.validate()?;
+
+    let tree = ProcessTree::load();
+    let protection = Protection::load(&tree, policy);
+    let mut results = Vec::new();
+    let mut pinned = Vec::new();
+    for &target in targets {
+        let name = tree.processes().get(&target.pid).map(|p| p.name.as_str());
+        if let Some(reason) = protection.reason(target.pid, name) {
+            results.push(KillResult { pid: Some(target.pid), outcome: KillOutcome::Protected { reason } });
+            continue;
+        }
+        match pin(target) {
+            // Checked against the table the protection was worked out from
+            Ok(process) if tree.processes().get(&process.pid).is_some_and(|p| p.start_ticks != process.start_ticks) => {
+                results.push(KillResult { pid: Some(target.pid), outcome: KillOutcome::AlreadyGone });
+            }
+            Ok(process) => pinned.push(process),
+            Err(outcome) => results.push(KillResult { pid: Some(target.pid), outcome }),
+        }
+    }
+    let pids: Vec<i32> = pinned.iter().map(|p| p.pid).collect();
+    let sockets = listening_sockets(&pids);
+
+    let mut signalled = Vec::new();
+    for process in &pinned {
+        match send(process, policy.signal) {
+            Ok(()) => signalled.push(process),
+            Err(outcome) => results.push(KillResult { pid: Some(process.pid), outcome }),
+        }
+    }
+
+    let graceful = wait_for_exit(&signalled, Duration::from_secs_f64(policy.timeout));
+    let mut escalated = Vec::new();
+    let running = if !graceful.is_empty() && policy.escalate && policy.signal != Signal::Kill {
+        for process in &graceful {
+            match send(process, Signal::Kill) {
+                Ok(()) => escalated.push(*process),
+                Err(outcome) => results.push(KillResult { pid: Some(process.pid), outcome }),
+            }
+        }
+        wait_for_exit(&escalated, SETTLE)
+    } else {
+        graceful.clone()
+    };
+
+    for process in &signalled {
+        let pid = process.pid;
+        let is = |list: &[&Pinned]| list.iter().any(|p| p.pid == pid);
+        let outcome = if is(&running) {
+            KillOutcome::StillRunning { signal: if is(&escalated) { Signal::Kill } else { policy.signal } }
+        } else if is(&escalated) {
+            KillOutcome::NeededKill { signal: policy.signal }
+        } else if is(&graceful) {
+            // Refused SIGKILL above
+            continue;
+        } else if policy.signal == Signal::Kill {
+            KillOutcome::Killed
+        } else {
+            KillOutcome::Exited { signal: policy.signal }
+        };
+        results.push(KillResult { pid: Some(pid), outcome });
+    }
+
+    // A child that inherited the listening fd keeps the port open after its parent is gone.
+    // Given as long as the processes got, and never less than the kernel needs to drop the sockets
+    if !sockets.is_empty() {
+        let start = Instant::now();
+        let release = Duration::from_secs_f64(policy.timeout).max(SETTLE);
+        let mut held: Vec<(i32, String)> = sockets.clone();
+        while !held.is_empty() && start.elapsed() < release {
+            std::thread::sleep(POLL);
+            let live: HashSet<i32> = sources::scan_listening().into_iter().map(|r| r.entry.inode).collect();
+            held.retain(|(inode, _)| live.contains(inode));
+        }
+        let still_running: Vec<i32> = running.iter().map(|p| p.pid).collect();
+        let holders = crate::get_pids_for_inodes(&held.iter().map(|(inode, _)| *inode).collect());
+        let mut ports: Vec<(String, Vec<i32>)> = Vec::new();
+        for (inode, port) in held {
+            // Sockets of processes that did not exit are covered by still_running; one with no
+            // holder we can see (another user's process) is still reported, the port is not free
+            let all: Vec<i32> = holders.get(&inode).cloned().unwrap_or_default();
+            let pids: Vec<i32> = all.iter().copied().filter(|p| !still_running.contains(p)).collect();
+            if !all.is_empty() && pids.is_empty() {
+                continue;
+            }
+            match ports.iter_mut().find(|(p, _)| *p == port) {
+                Some((_, all)) => all.extend(pids),
+                None => ports.push((port, pids)),
+            }
+        }
+        ports.sort();
+        for (port, mut pids) in ports {
+            pids.sort_unstable();
+            pids.dedup();
+            results.push(KillResult { pid: None, outcome: KillOutcome::PortHeld { port, pids } });
+        }
+    }
+
+    Ok(KillReport::new(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signals_by_name_or_number() {
+        assert_eq!("TERM".parse(), Ok(Signal::Term));
+        assert_eq!("sigkill".parse(), Ok(Signal::Kill));
+        assert_eq!("SIGHUP".parse(), Ok(Signal::Hup));
+        assert_eq!("2".parse(), Ok(Signal::Int));
+        assert_eq!("Quit".parse(), Ok(Signal::Quit));
+        assert!("USR1".parse::<Signal>().is_err());
+        assert!("SIG".parse::<Signal>().is_err());
+    }
+
+    fn result(pid: Option<i32>, outcome: KillOutcome) -> KillResult {
+        KillResult { pid, outcome }
+    }
+
+    #[test]
+    fn report_exit_code_is_the_most_severe_outcome() {
+        assert_eq!(KillReport::new(Vec::new()).exit_code(), 0);
+
+        let report = KillReport::new(vec![
+            result(Some(10), KillOutcome::Killed),
+            result(Some(11), KillOutcome::Exited { signal: Signal::Term }),
+        ]);
+        assert_eq!((report.exit_code(), report.killed, report.success), (0, 2, true));
+
+        let report = KillReport::new(vec![result(Some(10), KillOutcome::Killed), result(Some(11), KillOutcome::AlreadyGone)]);
+        assert_eq!((report.exit_code(), report.killed, report.success), (4, 1, false));
+
+        // Denied outranks failed and protected, a port still held outranks both
+        let mut report = KillReport::new(vec![
+            result(Some(1), KillOutcome::Protected { reason: "init".into() }),
+            result(Some(10), KillOutcome::Failed { error: "gone wrong".into() }),
+            result(Some(11), KillOutcome::PermissionDenied),
+        ]);
+        assert_eq!(report.exit_code(), 3);
+        report.push(result(None, KillOutcome::PortHeld { port: "8080/tcp".into(), pids: vec![12] }));
+        assert_eq!(report.exit_code(), 6);
+    }
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+
+use crate::config::{self, RuleSet, Subject};
+use crate::proc_root::{self, proc_path};
+use crate::{cmdline, get_pids_for_inodes};
+
+// __SO_ACCEPTCON, set on sockets that called listen()
+const SO_ACCEPTCON: u32 = 0x0001_0000;
+// SS_UNCONNECTED
+const STATE_UNCONNECTED: &str = "01";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnixSocketInfo {
+    // Filesystem path, or "@name" for abstract sockets
+    pub path: String,
+    pub is_abstract: bool,
+    // "stream", "dgram" or "seqpacket"
+    pub socket_type: String,
+    pub pid: Option<i32>,
+    pub process_name: Option<String>,
+    pub user: String,
+    // Start time of the owner in clock ticks, sent back with kills so a reused PID is refused
+    pub start_ticks: Option<u64>,
+}
+
+struct UnixEntry {
+    path: String,
+    socket_type: String,
+    inode: i32,
+}
+
+// Parse /proc/net/unix, keeping bound sockets a client could connect or send to
+fn scan_proc_net_unix(file: &str) -> Vec<UnixEntry> {
+    let mut results = Vec::new();
+    if let Ok(content) = fs::read_to_string(file) {
+        // Num RefCount Protocol Flags Type St Inode Path
+        for line in content.lines().skip(1) {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 8 { continue; }
+
+            let flags = u32::from_str_radix(parts[3], 16).unwrap_or(0);
+            let socket_type = match parts[4] {
+                "0001" => "stream",
+                "0002" => "dgram",
+                "0005" => "seqpacket",
+                _ => continue,
+            };
+            let listening = flags & SO_ACCEPTCON != 0;
+            let bound_dgram = socket_type == "dgram" && parts[5] == STATE_UNCONNECTED;
+            if !listening && !bound_dgram { continue; }
+
+            if let Ok(inode) = parts[6].parse::<i32>() {
+                // Paths may contain spaces, everything after the inode is the path
+                results.push(UnixEntry {
+                    path: parts[7..].join(" "),
+                    socket_type: socket_type.to_string(),
+                    inode,
+                });
+            }
+        }
+    }
+    results
+}
+
+pub(crate) fn get_unix_sockets_impl() -> Vec<UnixSocketInfo> {
+    let processes = proc_root::process_table();
+    let users = proc_root::user_names();
+    let rules = RuleSet::new(&config::load());
+
+    let entries = scan_proc_net_unix(&proc_path("net/unix"));
+    let inodes: HashSet<i32> = entries.iter().map(|e| e.inode).collect();
+    let inode_pid_map = get_pids_for_inodes(&inodes);
+
+    let mut sockets = Vec::new();
+
+    for entry in entries {
+        // Without a PID there is nothing we could kill
+        let Some(pid) = inode_pid_map.get(&entry.inode).and_then(|pids| pids.first().copied()) else { continue; };
+        let Some(process) = processes.get(&pid) else { continue; };
+
+        let mut user = "unknown".to_string();
+        if let Some(uid) = process.uid {
+            if let Some(name) = users.get(&uid) {
+                user = name.clone();
+            }
+        }
+
+        let cmdline = if rules.uses_cmdlines() { cmdline::cmdline_of(pid) } else { None };
+        let subject = Subject { port: None, process: Some(&process.name), user: Some(&user), cmdline: cmdline.as_deref() };
+        if rules.hides(&subject) {
+            continue;
+        }
+
+        sockets.push(UnixSocketInfo {
+            is_abstract: entry.path.starts_with('@'),
+            path: entry.path,
+            socket_type: entry.socket_type,
+            pid: Some(pid),
+            process_name: Some(process.name.clone()),
+            user,
+            start_ticks: Some(process.start_ticks),
+        });
+    }
+
+    sockets.sort_by(|a, b| a.path.cmp(&b.path));
+    sockets
+}
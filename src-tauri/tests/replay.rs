@@ -0,0 +1,84 @@
+// Filtering rules against a hand-made `ppkiller capture` (tests/fixtures/capture-basic):
+// sshd on 22 and nobody on 111 (system), avahi on udp 5353, vite on 5173, a forked
+// python http.server on [::]:8000, postgres (another user) on 5432, nobody on 9999
+// and a rootless podman nginx on 8080 in its own network namespace
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use app_lib::PortInfo;
+
+// The proc root and $XDG_CONFIG_HOME are process-wide, scans take turns
+static SCAN: Mutex<()> = Mutex::new(());
+
+fn scan(config: &str) -> Vec<PortInfo> {
+    let _guard = SCAN.lock().unwrap_or_else(|e| e.into_inner());
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    app_lib::set_proc_root(fixtures.join("capture-basic").to_str().unwrap()).unwrap();
+
+    let config_home = std::env::temp_dir().join(format!("ppkiller-replay-{}", std::process::id()));
+    std::fs::create_dir_all(config_home.join("ppkiller")).unwrap();
+    std::fs::write(config_home.join("ppkiller/config.toml"), config).unwrap();
+    std::env::set_var("XDG_CONFIG_HOME", &config_home);
+
+    app_lib::get_ports_list()
+}
+
+fn listed(ports: &[PortInfo]) -> Vec<String> {
+    ports.iter().map(|p| format!("{}/{}", p.port, p.protocol)).collect()
+}
+
+#[test]
+fn default_rules_hide_system_ports() {
+    let ports = scan("");
+    assert_eq!(listed(&ports), ["5173/tcp", "5432/tcp", "8000/tcp", "8080/tcp", "9999/tcp"]);
+
+    let vite = &ports[0];
+    assert_eq!((vite.pid, vite.user.as_str()), (Some(1200), "dev"));
+    assert_eq!(vite.display_name.as_deref(), Some("node vite"));
+    assert_eq!(vite.start_ticks, Some(3000));
+
+    // Nobody holds 9999, it is shown because it is not a system port
+    assert_eq!(ports[4].pid, None);
+}
+
+#[test]
+fn forked_holders_are_merged_into_one_row() {
+    let ports = scan("");
+    let python = ports.iter().find(|p| p.port == "8000").unwrap();
+    assert_eq!((python.family.as_str(), python.bind_address.as_str()), ("ipv6", "::"));
+    let owners: Vec<(i32, &str)> = python.owners.iter().map(|o| (o.pid, o.role.as_str())).collect();
+    assert_eq!(owners, [(1300, "parent"), (1301, "child")]);
+}
+
+#[test]
+fn containers_are_known_from_the_cgroup_alone() {
+    let ports = scan("");
+    let nginx = ports.iter().find(|p| p.port == "8080").unwrap();
+    assert!(nginx.namespaced);
+    assert_eq!(nginx.netns, "4026532500");
+    assert_eq!(nginx.container_id.as_deref(), Some("4f1c9b7e2d3a4f1c9b7e2d3a4f1c9b7e2d3a4f1c9b7e2d3a4f1c9b7e2d3a4f1c"));
+    // No API during a replay, the name is the short ID
+    assert_eq!(nginx.container_name.as_deref(), Some("4f1c9b7e2d3a"));
+}
+
+#[test]
+fn include_rules_bring_system_ports_back() {
+    let ports = scan("[include]\nports = [22]\nprocesses = [\"avahi-daemon\"]\n");
+    assert_eq!(listed(&ports), ["22/tcp", "5173/tcp", "5353/udp", "5432/tcp", "8000/tcp", "8080/tcp", "9999/tcp"]);
+    // A port nobody holds stays hidden below 1024 unless it is included itself
+    assert!(!ports.iter().any(|p| p.port == "111"));
+}
+
+#[test]
+fn ignore_rules_hide_users_processes_and_ranges() {
+    // Lists replace the defaults, they are not appended to
+    let ports = scan("[ignore]\nusers = [\"root\", \"postgres\"]\nprocesses = [\"python*\", \"sshd\", \"avahi-daemon\"]\nports = [\"9000-9999\"]\n");
+    assert_eq!(listed(&ports), ["5173/tcp", "8080/tcp"]);
+}
+
+#[test]
+fn cmdline_rules_match_the_full_command_line() {
+    let ports = scan("[ignore]\ncmdlines = [\"node_modules/\\\\.bin/vite\"]\n");
+    assert!(!ports.iter().any(|p| p.port == "5173"));
+    assert!(ports.iter().any(|p| p.port == "8000"));
+}